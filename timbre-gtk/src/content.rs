@@ -1,13 +1,18 @@
 use gtk;
 use gtk::prelude::*;
 
+use timbre;
 use timbre::games::octaves;
+use timbre::games::octaves::note::{Pitch, Tonality};
+use timbre::stats::{self, Confusion, Group, Period};
+use timbre::Database;
 
 #[derive(Clone)]
 pub struct Content {
     pub container: gtk::Box,
     pub revealer: gtk::Revealer,
     pub start_btn: gtk::Button,
+    pub mode_combo: gtk::ComboBoxText,
     pub tonality_combo: gtk::ComboBoxText,
 }
 
@@ -18,18 +23,28 @@ struct Statistics {
 }
 
 impl Content {
-    pub fn new(controller: &octaves::SharedController) -> Content {
+    pub fn new(controller: &octaves::SharedController, db: &Database) -> Content {
         let container = gtk::Box::new(gtk::Orientation::Horizontal, 0);
 
-        let ton_combo = gtk::ComboBoxText::new();
-        for tonality in octaves::note::TONALITIES.iter() {
-            ton_combo.append_text(&format!("{:?}", tonality));
+        let mode_combo = gtk::ComboBoxText::new();
+        for mode in octaves::note::MODES.iter() {
+            mode_combo.append_text(&mode.to_string());
         }
-        ton_combo.set_active(0);
+        mode_combo.set_active(0);
+
+        let ton_combo = gtk::ComboBoxText::new();
+        fill_tonality_combo(&ton_combo, octaves::note::Mode::Major);
+
+        mode_combo.connect_changed({
+            clone!(ton_combo);
+            move |combo| {
+                fill_tonality_combo(&ton_combo, active_mode(combo));
+            }
+        });
 
         let start_btn = gtk::Button::new_with_label("Start");
 
-        let stats = Content::build_statistics_panel();
+        let stats = Content::build_statistics_panel(db);
 
         let game_area = Content::build_game_area(controller, &stats);
         let revealer = gtk::Revealer::new();
@@ -42,7 +57,8 @@ impl Content {
 
         let ton_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
 
-        ton_box.pack_start(&ton_combo, false, false, 0);
+        ton_box.pack_start(&mode_combo, false, false, 0);
+        ton_box.pack_start(&ton_combo, false, false, 10);
         ton_box.pack_start(&start_btn, false, false, 10);
 
         left_box.pack_start(&ton_box, false, false, 10);
@@ -55,16 +71,23 @@ impl Content {
         container.pack_start(&right_box, false, false, 100);
 
         start_btn.connect_clicked({
-            clone!(controller, revealer, ton_combo);
+            clone!(controller, revealer, mode_combo, ton_combo);
             move |btn| {
+                let pos = ton_combo.get_active() as usize;
+                let tonalities = active_mode(&mode_combo).tonalities();
+                let tonality = tonalities.get(pos).unwrap();
+                if let Err(e) = controller.borrow_mut().new_game(*tonality) {
+                    show_error(&e.to_string());
+                    return;
+                }
+
                 btn.set_sensitive(false);
+                mode_combo.set_sensitive(false);
                 ton_combo.set_sensitive(false);
                 revealer.set_reveal_child(true);
 
-                let pos = ton_combo.get_active() as usize;
-                let tonality = octaves::note::TONALITIES.get(pos).unwrap();
-                controller.borrow_mut().new_game(*tonality);
-                controller.borrow().play_tonal_center();
+                let res = controller.borrow().play_intro();
+                report(res);
             }
         });
 
@@ -72,11 +95,29 @@ impl Content {
             container,
             start_btn,
             revealer,
+            mode_combo,
             tonality_combo: ton_combo,
         }
     }
 
-    fn build_statistics_panel() -> Statistics {
+    // Select the tonality in both combos
+    pub fn set_tonality(&self, tonality: octaves::note::Tonality) {
+        let mode_idx = octaves::note::MODES
+            .iter()
+            .position(|&m| m == tonality.mode)
+            .unwrap() as i32;
+        self.mode_combo.set_active(mode_idx);
+
+        let ton_idx = tonality
+            .mode
+            .tonalities()
+            .iter()
+            .position(|&t| t == tonality)
+            .unwrap() as i32;
+        self.tonality_combo.set_active(ton_idx);
+    }
+
+    fn build_statistics_panel(db: &Database) -> Statistics {
         let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
         let box_1 = gtk::Box::new(gtk::Orientation::Horizontal, 0);
@@ -100,15 +141,15 @@ impl Content {
         use timbre::schema::{octave_game_states, octave_games};
         use timbre::games::octaves::models;
 
-        let conn = timbre::establish_connection();
         let history = octave_games::table
             .inner_join(octave_game_states::table)
             .select(octave_game_states::all_columns)
             .filter(octave_games::finished_at.is_not_null())
             .order(octave_games::created_at.desc())
             .limit(5)
-            .load::<models::GameState>(&conn)
-            .unwrap();
+            .load::<models::GameState>(db.connection())
+            .map_err(timbre::Error::from);
+        let history = report(history).unwrap_or_default();
 
         for (i, game) in history.iter().enumerate() {
             let s = format!(
@@ -122,9 +163,55 @@ impl Content {
             box_3.pack_start(&l, false, false, 0);
         }
 
+        let box_4 = gtk::Box::new(gtk::Orientation::Vertical, 0);
+
+        let degrees = stats::accuracy(db.connection(), Group::Degree, Period::last_days(30))
+            .map_err(timbre::Error::from);
+        let degrees = report(degrees).unwrap_or_default();
+
+        if !degrees.is_empty() {
+            let l = gtk::Label::new("Degrees, last 30 days:");
+            box_4.pack_start(&l, false, false, 0);
+        }
+
+        for degree in degrees.iter() {
+            let time = degree.response_time;
+            let s = format!(
+                "{}: {:.0}%, {}.{} s",
+                degree.key,
+                degree.ratio() * 100.0,
+                time.as_secs(),
+                time.subsec_nanos() / 100_000_000,
+            );
+            let l = gtk::Label::new(s.as_str());
+            box_4.pack_start(&l, false, false, 0);
+        }
+
+        let box_5 = gtk::Box::new(gtk::Orientation::Vertical, 0);
+
+        // Confusions in the exercise of the last game
+        let last_game = history
+            .first()
+            .and_then(|g| g.tonality.parse::<Tonality>().ok().map(|t| (t, g.exercise)));
+        if let Some((tonality, exercise)) = last_game {
+            let matrix =
+                stats::confusion(db.connection(), tonality, exercise as u8, Period::all())
+                    .map_err(timbre::Error::from);
+            let matrix = report(matrix).unwrap_or_default();
+
+            if !matrix.is_empty() {
+                let s = format!("Answers in {}, ex. #{}:", tonality, exercise);
+                let l = gtk::Label::new(s.as_str());
+                box_5.pack_start(&l, false, false, 0);
+                box_5.pack_start(&build_heat_map(&matrix), false, false, 5);
+            }
+        }
+
         container.pack_start(&box_1, false, false, 0);
         container.pack_start(&box_2, false, false, 0);
         container.pack_end(&box_3, false, false, 0);
+        container.pack_end(&box_4, false, false, 10);
+        container.pack_end(&box_5, false, false, 10);
 
         Statistics {
             container,
@@ -152,7 +239,8 @@ impl Content {
         play_chord_btn.connect_clicked({
             clone!(controller);
             move |_| {
-                controller.borrow().play_tonal_center();
+                let res = controller.borrow().play_intro();
+                report(res);
             }
         });
 
@@ -164,7 +252,8 @@ impl Content {
         play_btn.connect_clicked({
             clone!(controller);
             move |_| {
-                controller.borrow().repeat_note();
+                let res = controller.borrow_mut().repeat_question();
+                report(res);
             }
         });
 
@@ -179,7 +268,8 @@ impl Content {
         next_btn.connect_clicked({
             clone!(controller);
             move |_| {
-                controller.borrow_mut().play_next_note();
+                let res = controller.borrow_mut().play_next_question();
+                report(res);
             }
         });
 
@@ -193,18 +283,22 @@ impl Content {
 
         macro_rules! answer {
             ($btn:ident) => {{
-                let label = $btn.get_label().unwrap();
+                let pitch = $btn.get_label().unwrap().parse::<Pitch>().unwrap();
+                answer!($btn, pitch);
+            }};
+            ($btn:ident, $pitch:expr) => {{
+                let pitch = $pitch;
                 $btn.connect_clicked({
                     let btn = $btn.clone();
                     clone!(controller);
                     move |_| {
-                        let answers: [&str; 1] = [&label];
-                        if let Some(res) = controller.borrow_mut().check_answers(&answers) {
+                        let res = controller.borrow_mut().check_answer(&pitch);
+                        if let Some(Some(res)) = report(res) {
                             toggle_btn_class(&btn, res);
                         }
                     }
                 });
-            }}
+            }};
         }
 
         answer!(c_btn);
@@ -221,26 +315,12 @@ impl Content {
         let gsharp_btn = gtk::Button::new_with_label("# / b");
         let asharp_btn = gtk::Button::new_with_label("# / b");
 
-        macro_rules! alt_answer {
-            ($btn:ident, $sharp:expr, $flat:expr) => {{
-                $btn.connect_clicked({
-                    let btn = $btn.clone();
-                    clone!(controller);
-                    move |_| {
-                        let answers: [&str; 2] = [&$sharp, &$flat];
-                        if let Some(res) = controller.borrow_mut().check_answers(&answers) {
-                            toggle_btn_class(&btn, res);
-                        }
-                    }
-                });
-            }}
-        }
-
-        alt_answer!(csharp_btn, "Csharp", "Dflat");
-        alt_answer!(dsharp_btn, "Dsharp", "Eflat");
-        alt_answer!(fsharp_btn, "Fsharp", "Gflat");
-        alt_answer!(gsharp_btn, "Gsharp", "Aflat");
-        alt_answer!(asharp_btn, "Asharp", "Bflat");
+        // The sounding pitch is checked, so the sharp stands for the flat too
+        answer!(csharp_btn, Pitch::Csharp);
+        answer!(dsharp_btn, Pitch::Dsharp);
+        answer!(fsharp_btn, Pitch::Fsharp);
+        answer!(gsharp_btn, Pitch::Gsharp);
+        answer!(asharp_btn, Pitch::Asharp);
 
         controller.borrow_mut().add_count_observer({
             let label = stats.total_label.clone();
@@ -297,7 +377,43 @@ impl Content {
     }
 }
 
-fn toggle_btn_class(btn: &gtk::Button, flag: bool) {
+fn active_mode(combo: &gtk::ComboBoxText) -> octaves::note::Mode {
+    let pos = combo.get_active() as usize;
+    octaves::note::MODES.get(pos).cloned().unwrap()
+}
+
+fn fill_tonality_combo(combo: &gtk::ComboBoxText, mode: octaves::note::Mode) {
+    combo.remove_all();
+    for tonality in mode.tonalities().iter() {
+        combo.append_text(&tonality.key.to_string());
+    }
+    combo.set_active(0);
+}
+
+// Show the error of a library call, if any
+pub fn report<T>(result: Result<T, timbre::Error>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            show_error(&e.to_string());
+            None
+        }
+    }
+}
+
+pub fn show_error(text: &str) {
+    let dialog = gtk::MessageDialog::new(
+        None::<&gtk::Window>,
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Close,
+        text,
+    );
+    dialog.run();
+    dialog.destroy();
+}
+
+pub fn toggle_btn_class(btn: &gtk::Button, flag: bool) {
     let btn_class = if flag {
         "suggested-action"
     } else {
@@ -314,3 +430,43 @@ fn toggle_btn_class(btn: &gtk::Button, flag: bool) {
         }
     });
 }
+
+// Played pitches by rows and given ones by columns,
+// the more often the answer the redder the cell
+fn build_heat_map(matrix: &Confusion) -> gtk::Grid {
+    let grid = gtk::Grid::new();
+    grid.set_halign(gtk::Align::Center);
+    grid.set_row_spacing(2);
+    grid.set_column_spacing(2);
+
+    let pitches = matrix.pitches();
+    for (i, pitch) in pitches.iter().enumerate() {
+        let pos = i as i32 + 1;
+        grid.attach(&gtk::Label::new(pitch.to_string().as_str()), pos, 0, 1, 1);
+        grid.attach(&gtk::Label::new(pitch.to_string().as_str()), 0, pos, 1, 1);
+    }
+
+    for (row, &played) in pitches.iter().enumerate() {
+        let total = matrix.total(played);
+
+        for (col, &given) in pitches.iter().enumerate() {
+            let count = matrix.count(played, given);
+            let share = if total == 0 {
+                0.0
+            } else {
+                count as f32 / total as f32
+            };
+            let shade = 255 - (share * 255.0) as u8;
+
+            let l = gtk::Label::new(None);
+            l.set_markup(&format!(
+                "<span background=\"#ff{:02x}{:02x}\"> {:>2} </span>",
+                shade, shade, count
+            ));
+            l.set_tooltip_text(format!("{} heard as {}", played, given).as_str());
+            grid.attach(&l, col as i32 + 1, row as i32 + 1, 1, 1);
+        }
+    }
+
+    grid
+}
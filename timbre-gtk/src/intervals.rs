@@ -0,0 +1,135 @@
+use gtk;
+use gtk::prelude::*;
+
+use timbre::games::intervals;
+use timbre::games::intervals::interval::INTERVALS;
+
+use content::{report, show_error, toggle_btn_class};
+
+// Answer buttons per row
+const ROW_LEN: usize = 6;
+
+#[derive(Clone)]
+pub struct IntervalsPage {
+    pub container: gtk::Box,
+    pub revealer: gtk::Revealer,
+    pub start_btn: gtk::Button,
+}
+
+impl IntervalsPage {
+    pub fn new(controller: &intervals::SharedController) -> IntervalsPage {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+
+        let start_btn = gtk::Button::new_with_label("Start");
+        start_btn.set_halign(gtk::Align::Start);
+
+        let game_area = IntervalsPage::build_game_area(controller);
+        let revealer = gtk::Revealer::new();
+        revealer.set_transition_type(gtk::RevealerTransitionType::Crossfade);
+        revealer.set_transition_duration(2000);
+        revealer.add(&game_area);
+
+        container.pack_start(&start_btn, false, false, 10);
+        container.pack_start(&revealer, true, true, 0);
+
+        start_btn.connect_clicked({
+            clone!(controller, revealer);
+            move |btn| {
+                if let Err(e) = controller.borrow_mut().new_game(()) {
+                    show_error(&e.to_string());
+                    return;
+                }
+
+                btn.set_sensitive(false);
+                revealer.set_reveal_child(true);
+            }
+        });
+
+        IntervalsPage {
+            container,
+            revealer,
+            start_btn,
+        }
+    }
+
+    fn build_game_area(controller: &intervals::SharedController) -> gtk::Box {
+        let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        v_box.set_halign(gtk::Align::Center);
+        v_box.set_valign(gtk::Align::Center);
+
+        let btns = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        btns.set_halign(gtk::Align::Center);
+
+        let play_btn = gtk::Button::new_from_icon_name(
+            "media-playlist-repeat-symbolic",
+            gtk::IconSize::Button.into(),
+        );
+        play_btn.set_tooltip_text("Repeat interval");
+        play_btn.connect_clicked({
+            clone!(controller);
+            move |_| {
+                let res = controller.borrow_mut().repeat_question();
+                report(res);
+            }
+        });
+
+        let next_btn = gtk::Button::new_from_icon_name(
+            "media-playback-start-symbolic",
+            gtk::IconSize::Button.into(),
+        );
+        next_btn.set_tooltip_text("Play next interval");
+        next_btn
+            .get_style_context()
+            .map(|x| x.add_class("suggested-action"));
+        next_btn.connect_clicked({
+            clone!(controller);
+            move |_| {
+                let res = controller.borrow_mut().play_next_question();
+                report(res);
+            }
+        });
+
+        let answers = gtk::Grid::new();
+        answers.set_halign(gtk::Align::Center);
+
+        for (i, &interval) in INTERVALS.iter().enumerate() {
+            let btn = gtk::Button::new_with_label(&interval.to_string());
+            btn.connect_clicked({
+                clone!(controller);
+                move |btn| {
+                    let res = controller.borrow_mut().check_answer(&interval);
+                    if let Some(Some(res)) = report(res) {
+                        toggle_btn_class(btn, res);
+                    }
+                }
+            });
+
+            answers.attach(&btn, (i % ROW_LEN) as i32, (i / ROW_LEN) as i32, 1, 1);
+        }
+
+        let stats = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        stats.set_halign(gtk::Align::Center);
+
+        let right_label = gtk::Label::new("0");
+        let total_label = gtk::Label::new("0");
+
+        stats.pack_start(&gtk::Label::new("Right: "), false, false, 0);
+        stats.pack_start(&right_label, false, false, 0);
+        stats.pack_start(&gtk::Label::new(" / Total: "), false, false, 0);
+        stats.pack_start(&total_label, false, false, 0);
+
+        controller.borrow_mut().add_count_observer(move |ctrl| {
+            right_label.set_text(&ctrl.right_count().to_string());
+            total_label.set_text(&ctrl.total_count().to_string());
+        });
+
+        btns.pack_start(&play_btn, false, false, 0);
+        btns.pack_start(&next_btn, false, false, 0);
+
+        v_box.pack_start(&btns, false, false, 20);
+        v_box.pack_start(&answers, false, false, 0);
+        v_box.pack_start(&stats, false, false, 20);
+
+        v_box
+    }
+}
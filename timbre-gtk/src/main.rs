@@ -3,12 +3,18 @@ extern crate gio;
 extern crate gtk;
 extern crate timbre;
 
+use std::rc::Rc;
+
 use gio::{ApplicationExt, ApplicationExtManual};
 use gtk::prelude::*;
-use timbre::games::octaves;
+use timbre::config::Config;
+use timbre::games::{chords, intervals, octaves};
 use timbre::sampler;
+use timbre::Database;
 
-use content::Content;
+use chords::ChordsPage;
+use content::{report, show_error, Content};
+use intervals::IntervalsPage;
 
 macro_rules! clone {
     ($($n:ident),+) => {
@@ -16,7 +22,9 @@ macro_rules! clone {
     }
 }
 
+mod chords;
 mod content;
+mod intervals;
 
 struct App {
     pub window: gtk::ApplicationWindow,
@@ -25,10 +33,30 @@ struct App {
 
 impl App {
     fn new(gtk_app: &gtk::Application) -> App {
-        let config = octaves::Config::load();
-        let controller = octaves::Controller::new_shared(config);
-
-        let window = build_window(gtk_app, &controller);
+        // The defaults are played with until the config is fixed
+        let config = Config::load().unwrap_or_else(|e| {
+            show_error(&e.to_string());
+            Config::default()
+        });
+        // The games still work without the saved ones
+        let db = Database::open_default().unwrap_or_else(|e| {
+            show_error(&e.to_string());
+            Database::in_memory().expect("Failed to create a database")
+        });
+        // The built-in exercises are used instead of the invalid ones
+        if let Some(e) = octaves::exercise::load_error() {
+            show_error(&e.to_string());
+        }
+        let controller = octaves::Controller::new_shared(config.clone(), db.clone());
+        let intervals_controller = intervals::Controller::new_shared(config.clone(), db.clone());
+        let chords_controller = chords::Controller::new_shared(config.clone(), db.clone());
+
+        let window = build_window(
+            gtk_app,
+            &controller,
+            &intervals_controller,
+            &chords_controller,
+        );
         window.set_resizable(false);
 
         let header = gtk::HeaderBar::new();
@@ -36,14 +64,34 @@ impl App {
         header.set_show_close_button(true);
         window.set_titlebar(&header);
 
-        let content = Content::new(&controller);
+        let content = Content::new(&controller, &db);
+        let intervals_page = IntervalsPage::new(&intervals_controller);
+        let chords_page = ChordsPage::new(&chords_controller);
+
+        let notebook = gtk::Notebook::new();
+        notebook.append_page(&content.container, Some(&gtk::Label::new("Octaves")));
+        notebook.append_page(
+            &intervals_page.container,
+            Some(&gtk::Label::new("Intervals")),
+        );
+        notebook.append_page(&chords_page.container, Some(&gtk::Label::new("Chords")));
+
         let menu_bar = gtk::MenuBar::new();
-        menu_bar.append(&build_game_menu(&window, &content, &controller));
-        menu_bar.append(&build_control_menu(&window));
+        menu_bar.append(&build_game_menu(
+            &window,
+            &notebook,
+            &content,
+            &controller,
+            &intervals_page,
+            &intervals_controller,
+            &chords_page,
+            &chords_controller,
+        ));
+        menu_bar.append(&build_control_menu(&window, config));
 
         let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
         v_box.pack_start(&menu_bar, false, false, 0);
-        v_box.pack_start(&content.container, true, true, 0);
+        v_box.pack_start(&notebook, true, true, 0);
 
         window.add(&v_box);
 
@@ -51,40 +99,49 @@ impl App {
             clone!(window);
             move |exercise: &'static octaves::Exercise| {
                 println!("Ex observer: {:?}", exercise);
-
-                let dialog = gtk::MessageDialog::new(
-                    Some(&window),
-                    gtk::DialogFlags::MODAL,
-                    gtk::MessageType::Info,
-                    gtk::ButtonsType::Ok,
-                    &format!("Great!\n Let's start exercise #{}", exercise.num),
-                );
-                let ok: i32 = gtk::ResponseType::Ok.into();
-                if dialog.run() == ok {
-                    dialog.destroy();
-                }
+                show_next_exercise(&window, exercise.num);
             }
         };
         controller.borrow_mut().next_exercise_observer = Some(Box::new(observer));
 
         let observer = {
             clone!(window);
-            move || {
-                let dialog = gtk::MessageDialog::new(
-                    Some(&window),
-                    gtk::DialogFlags::MODAL,
-                    gtk::MessageType::Info,
-                    gtk::ButtonsType::Ok,
-                    &format!("Game over"),
-                );
-                let ok: i32 = gtk::ResponseType::Ok.into();
-                if dialog.run() == ok {
-                    dialog.destroy();
-                }
-            }
+            move || show_game_over(&window)
         };
         controller.borrow_mut().game_over_observer = Some(Box::new(observer));
 
+        let observer = {
+            clone!(window);
+            move |exercise: &'static intervals::Exercise| {
+                println!("Ex observer: {:?}", exercise);
+                show_next_exercise(&window, exercise.num);
+            }
+        };
+        intervals_controller.borrow_mut().next_exercise_observer = Some(Box::new(observer));
+
+        let observer = {
+            clone!(window);
+            move || show_game_over(&window)
+        };
+        intervals_controller.borrow_mut().game_over_observer = Some(Box::new(observer));
+
+        let observer = {
+            clone!(window);
+            let inversion_combo = chords_page.inversion_combo.clone();
+            move |exercise: &'static chords::Exercise| {
+                println!("Ex observer: {:?}", exercise);
+                inversion_combo.set_sensitive(exercise.name_inversion);
+                show_next_exercise(&window, exercise.num);
+            }
+        };
+        chords_controller.borrow_mut().next_exercise_observer = Some(Box::new(observer));
+
+        let observer = {
+            clone!(window);
+            move || show_game_over(&window)
+        };
+        chords_controller.borrow_mut().game_over_observer = Some(Box::new(observer));
+
         App { window, content }
     }
 
@@ -96,9 +153,39 @@ impl App {
     }
 }
 
+fn show_next_exercise(window: &gtk::ApplicationWindow, num: u8) {
+    let dialog = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Info,
+        gtk::ButtonsType::Ok,
+        &format!("Great!\n Let's start exercise #{}", num),
+    );
+    let ok: i32 = gtk::ResponseType::Ok.into();
+    if dialog.run() == ok {
+        dialog.destroy();
+    }
+}
+
+fn show_game_over(window: &gtk::ApplicationWindow) {
+    let dialog = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Info,
+        gtk::ButtonsType::Ok,
+        &format!("Game over"),
+    );
+    let ok: i32 = gtk::ResponseType::Ok.into();
+    if dialog.run() == ok {
+        dialog.destroy();
+    }
+}
+
 fn build_window(
     app: &gtk::Application,
     controller: &octaves::SharedController,
+    intervals_controller: &intervals::SharedController,
+    chords_controller: &chords::SharedController,
 ) -> gtk::ApplicationWindow {
     let window = gtk::ApplicationWindow::new(app);
 
@@ -108,12 +195,27 @@ fn build_window(
     window.set_default_size(800, 500);
 
     window.connect_delete_event({
-        clone!(controller, window);
+        clone!(controller, intervals_controller, chords_controller, window);
         move |_, _| {
-            if controller.borrow().is_finished() {
+            let octaves_running = !controller.borrow().is_finished();
+            let intervals_running = !intervals_controller.borrow().is_finished();
+            let chords_running = !chords_controller.borrow().is_finished();
+
+            if !octaves_running && !intervals_running && !chords_running {
                 window.destroy();
             } else {
-                controller.borrow().save_state();
+                if octaves_running {
+                    let res = controller.borrow().save_state();
+                    report(res);
+                }
+                if intervals_running {
+                    let res = intervals_controller.borrow().save_state();
+                    report(res);
+                }
+                if chords_running {
+                    let res = chords_controller.borrow().save_state();
+                    report(res);
+                }
 
                 let dialog = gtk::MessageDialog::new(
                     Some(&window),
@@ -124,7 +226,18 @@ fn build_window(
                 );
                 let no: i32 = gtk::ResponseType::No.into();
                 if dialog.run() == no {
-                    controller.borrow().finish_game();
+                    if octaves_running {
+                        let res = controller.borrow().finish_game();
+                        report(res);
+                    }
+                    if intervals_running {
+                        let res = intervals_controller.borrow().finish_game();
+                        report(res);
+                    }
+                    if chords_running {
+                        let res = chords_controller.borrow().finish_game();
+                        report(res);
+                    }
                 }
                 dialog.destroy();
             }
@@ -146,10 +259,20 @@ macro_rules! build_menu {
     }}
 }
 
+// Page indices in the notebook
+const OCTAVES_PAGE: u32 = 0;
+const INTERVALS_PAGE: u32 = 1;
+const CHORDS_PAGE: u32 = 2;
+
 fn build_game_menu(
     window: &gtk::ApplicationWindow,
+    notebook: &gtk::Notebook,
     content: &Content,
     controller: &octaves::SharedController,
+    intervals_page: &IntervalsPage,
+    intervals_controller: &intervals::SharedController,
+    chords_page: &ChordsPage,
+    chords_controller: &chords::SharedController,
 ) -> gtk::MenuItem {
     let new = gtk::MenuItem::new_with_mnemonic("_New");
     let load = gtk::MenuItem::new_with_mnemonic("_Load");
@@ -158,42 +281,87 @@ fn build_game_menu(
 
     // TODO: prompt to save the game
     new.connect_activate({
-        let revealer = content.revealer.clone();
-        let start_btn = content.start_btn.clone();
-        let ton_combo = content.tonality_combo.clone();
-        move |_| {
-            revealer.set_reveal_child(false);
-            start_btn.set_sensitive(true);
-            ton_combo.set_sensitive(true);
+        clone!(notebook);
+        let content = content.clone();
+        let intervals_page = intervals_page.clone();
+        let chords_page = chords_page.clone();
+        move |_| match notebook.get_current_page() {
+            Some(OCTAVES_PAGE) => {
+                content.revealer.set_reveal_child(false);
+                content.start_btn.set_sensitive(true);
+                content.mode_combo.set_sensitive(true);
+                content.tonality_combo.set_sensitive(true);
+            }
+            Some(INTERVALS_PAGE) => {
+                intervals_page.revealer.set_reveal_child(false);
+                intervals_page.start_btn.set_sensitive(true);
+            }
+            Some(CHORDS_PAGE) => {
+                chords_page.revealer.set_reveal_child(false);
+                chords_page.start_btn.set_sensitive(true);
+            }
+            _ => (),
         }
     });
 
     load.connect_activate({
-        clone!(controller, window);
-        let revealer = content.revealer.clone();
-        let start_btn = content.start_btn.clone();
-        let ton_combo = content.tonality_combo.clone();
+        clone!(
+            controller,
+            intervals_controller,
+            chords_controller,
+            window,
+            notebook
+        );
+        let content = content.clone();
+        let intervals_page = intervals_page.clone();
+        let chords_page = chords_page.clone();
 
         move |_| {
-            let game_state = controller.borrow_mut().load_game();
-            let msg: &str = match game_state {
-                Some(ref s) => {
-                    revealer.set_reveal_child(true);
-                    start_btn.set_sensitive(false);
-                    ton_combo.set_sensitive(false);
-
-                    let tonality = s.tonality.parse::<octaves::note::Tonality>().unwrap();
-                    let ton_idx: i32 = octaves::note::TONALITIES
-                        .iter()
-                        .position(|&t| t == tonality)
-                        .unwrap() as i32;
-                    ton_combo.set_active(ton_idx);
-
-                    controller.borrow().play_tonal_center();
-
-                    "Game is loaded"
+            let loaded = match notebook.get_current_page() {
+                Some(OCTAVES_PAGE) => {
+                    let loaded = controller.borrow_mut().load_game();
+                    if let Ok(true) = loaded {
+                        content.revealer.set_reveal_child(true);
+                        content.start_btn.set_sensitive(false);
+                        content.mode_combo.set_sensitive(false);
+                        content.tonality_combo.set_sensitive(false);
+
+                        let tonality = controller.borrow().game().map(|g| g.tonality);
+                        tonality.map(|t| content.set_tonality(t));
+
+                        let res = controller.borrow().play_intro();
+                        report(res);
+                    }
+                    loaded
+                }
+                Some(INTERVALS_PAGE) => {
+                    let loaded = intervals_controller.borrow_mut().load_game();
+                    if let Ok(true) = loaded {
+                        intervals_page.revealer.set_reveal_child(true);
+                        intervals_page.start_btn.set_sensitive(false);
+                    }
+                    loaded
                 }
-                None => "Game not found",
+                Some(CHORDS_PAGE) => {
+                    let loaded = chords_controller.borrow_mut().load_game();
+                    if let Ok(true) = loaded {
+                        chords_page.revealer.set_reveal_child(true);
+                        chords_page.start_btn.set_sensitive(false);
+
+                        let name_inversion = chords_controller
+                            .borrow()
+                            .game()
+                            .map_or(false, |g| g.exercise.name_inversion);
+                        chords_page.inversion_combo.set_sensitive(name_inversion);
+                    }
+                    loaded
+                }
+                _ => Ok(false),
+            };
+            let msg = match loaded {
+                Ok(true) => "Game is loaded".to_owned(),
+                Ok(false) => "Game not found".to_owned(),
+                Err(e) => e.to_string(),
             };
 
             let dialog = gtk::MessageDialog::new(
@@ -201,7 +369,7 @@ fn build_game_menu(
                 gtk::DialogFlags::MODAL,
                 gtk::MessageType::Info,
                 gtk::ButtonsType::Ok,
-                msg,
+                &msg,
             );
             let ok: i32 = gtk::ResponseType::Ok.into();
             if dialog.run() == ok {
@@ -221,45 +389,119 @@ fn build_game_menu(
     )
 }
 
-fn build_control_menu(window: &gtk::ApplicationWindow) -> gtk::MenuItem {
+fn build_control_menu(window: &gtk::ApplicationWindow, config: Config) -> gtk::MenuItem {
     let sync = gtk::MenuItem::new_with_mnemonic("_Synchronize samples");
 
     sync.connect_activate({
         clone!(window);
         move |_| {
-            let dialog = gtk::MessageDialog::new(
-                Some(&window),
-                gtk::DialogFlags::MODAL,
-                gtk::MessageType::Info,
-                gtk::ButtonsType::None,
-                "Synchronization...",
-            );
-            dialog.show_now();
-
-            let (type_, text) = match sampler::sync_files() {
-                Ok(_) => (gtk::MessageType::Info, "Done".to_string()),
-                Err(e) => (gtk::MessageType::Error, format!("{}", e)),
+            let handle = match sampler::sync_files(&config) {
+                Ok(handle) => Rc::new(handle),
+                Err(e) => {
+                    show_sync_result(&window, gtk::MessageType::Error, &e.to_string());
+                    return;
+                }
             };
-            dialog.destroy();
 
-            let dialog = gtk::MessageDialog::new(
+            let cancel: i32 = gtk::ResponseType::Cancel.into();
+            let dialog = gtk::Dialog::new_with_buttons(
+                Some("Synchronization"),
                 Some(&window),
                 gtk::DialogFlags::MODAL,
-                type_,
-                gtk::ButtonsType::Close,
-                &text,
+                &[("_Cancel", cancel)],
             );
-
-            let close_type: i32 = gtk::ResponseType::Close.into();
-            if dialog.run() == close_type {
-                dialog.destroy();
-            }
+            let progress_bar = gtk::ProgressBar::new();
+            progress_bar.set_show_text(true);
+            progress_bar.set_text("Starting...");
+            dialog
+                .get_content_area()
+                .pack_start(&progress_bar, true, true, 10);
+            dialog.show_all();
+
+            // Closing the dialog cancels the sync as well
+            dialog.connect_response({
+                clone!(handle, progress_bar);
+                move |dialog, _| {
+                    handle.cancel();
+                    progress_bar.set_text("Cancelling...");
+                    dialog.set_response_sensitive(cancel, false);
+                }
+            });
+
+            gtk::timeout_add(100, {
+                clone!(window);
+                move || {
+                    for progress in handle.progress() {
+                        show_sync_progress(&progress_bar, &progress);
+                    }
+
+                    match handle.result() {
+                        None => gtk::Continue(true),
+                        Some(result) => {
+                            dialog.destroy();
+                            match result {
+                                Ok(_) => show_sync_result(&window, gtk::MessageType::Info, "Done"),
+                                Err(e) => show_sync_result(
+                                    &window,
+                                    gtk::MessageType::Error,
+                                    &e.to_string(),
+                                ),
+                            }
+                            gtk::Continue(false)
+                        }
+                    }
+                }
+            });
         }
     });
 
     build_menu!("_Control", [sync])
 }
 
+fn show_sync_progress(progress_bar: &gtk::ProgressBar, progress: &sampler::Progress) {
+    const MB: f64 = 1024.0 * 1024.0;
+
+    // The size of the steps other than a download isn't known beforehand
+    let (fraction, text) = match *progress {
+        sampler::Progress::Fetching(ref source) => (None, format!("Fetching {}", source)),
+        sampler::Progress::Downloaded(bytes, Some(total)) if total > 0 => (
+            Some(bytes as f64 / total as f64),
+            format!(
+                "Downloaded {:.1} of {:.1} MB",
+                bytes as f64 / MB,
+                total as f64 / MB
+            ),
+        ),
+        sampler::Progress::Downloaded(bytes, _) => {
+            (None, format!("Downloaded {:.1} MB", bytes as f64 / MB))
+        }
+        sampler::Progress::Extracted(count) => (None, format!("Extracted {} files", count)),
+        sampler::Progress::Verifying => (None, "Verifying...".to_owned()),
+        sampler::Progress::Installing => (None, "Installing...".to_owned()),
+    };
+
+    match fraction {
+        Some(fraction) => progress_bar.set_fraction(fraction),
+        None => progress_bar.pulse(),
+    }
+    progress_bar.set_text(text.as_str());
+}
+
+fn show_sync_result(window: &gtk::ApplicationWindow, type_: gtk::MessageType, text: &str) {
+    let dialog = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        type_,
+        gtk::ButtonsType::Close,
+        text,
+    );
+
+    let close_type: i32 = gtk::ResponseType::Close.into();
+    if dialog.run() == close_type {
+        dialog.destroy();
+    }
+}
+
 fn main() {
     let app = gtk::Application::new(
         "com.github.ivanovaleksey.timbre",
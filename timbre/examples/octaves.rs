@@ -1,39 +1,51 @@
 extern crate timbre;
 
+use std::thread;
+use std::time::Duration;
+
+use timbre::config::Config;
 use timbre::games::octaves;
+use timbre::{Database, Error};
 
 fn main() {
-    let config = octaves::Config::load();
+    if let Err(e) = run() {
+        println!("{}", e);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let config = Config::load()?;
     let config_1 = config.clone();
-    let mut controller = octaves::Controller::new(config);
+    let mut controller = octaves::Controller::new(config, Database::open_default()?);
 
     let tonality = octaves::note::TONALITIES.first().unwrap();
-    controller.new_game(*tonality);
+    controller.new_game(*tonality)?;
 
-    controller.play_tonal_center();
+    controller.play_intro()?;
 
-    controller.play_next_note();
-    controller.repeat_note();
+    controller.play_next_question()?;
+    controller.repeat_question()?;
 
-    controller.play_next_note();
-    controller.play_next_note();
-    controller.repeat_note();
+    controller.play_next_question()?;
+    controller.play_next_question()?;
+    controller.repeat_question()?;
 
-    controller.play_next_note();
-    controller.play_next_note();
-    controller.repeat_note();
+    controller.play_next_question()?;
+    controller.play_next_question()?;
+    controller.repeat_question()?;
 
-    controller.play_next_note();
-    controller.play_next_note();
-    controller.repeat_note();
+    controller.play_next_question()?;
+    controller.play_next_question()?;
+    controller.repeat_question()?;
 
-    controller.play_next_note();
-    controller.play_next_note();
-    controller.repeat_note();
+    controller.play_next_question()?;
+    controller.play_next_question()?;
+    controller.repeat_question()?;
 
-    controller.play_next_note();
-    controller.repeat_note();
+    controller.play_next_question()?;
+    controller.repeat_question()?;
 
-    config_1.save();
-    std::thread::sleep_ms(1000 * 60 * 2);
+    config_1.save()?;
+    thread::sleep(Duration::from_millis(1000 * 60 * 2));
+    Ok(())
 }
@@ -0,0 +1,34 @@
+extern crate timbre;
+
+use std::env;
+use std::fs;
+
+use timbre::config::Config;
+use timbre::games::octaves::note::Tonality;
+use timbre::games::octaves::{State, EXERCISES};
+use timbre::games::render_exercise;
+
+// cargo run --example render -- Dmaj 3 exercise.wav
+// The same seed gives the same order of the notes
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() != 4 && args.len() != 5 {
+        println!("Usage: {} TONALITY EXERCISE FILE [SEED]", args[0]);
+        return;
+    }
+
+    let tonality = args[1].parse::<Tonality>().expect("Unknown tonality");
+    let num = args[2].parse::<u8>().expect("Invalid exercise number");
+    let exercise = EXERCISES
+        .iter()
+        .find(|ex| ex.num == num)
+        .cloned()
+        .expect("No such exercise");
+
+    let seed = args.get(4).map_or(0, |s| s.parse::<u64>().expect("Invalid seed"));
+
+    let state = State::new(tonality, exercise, seed);
+    let mut file = fs::File::create(&args[3]).expect("Couldn't create file");
+    let config = Config::load().expect("Couldn't read the config");
+    render_exercise(state, &config, 2.0, &mut file).expect("Couldn't write file");
+}
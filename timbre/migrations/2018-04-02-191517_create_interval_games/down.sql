@@ -0,0 +1 @@
+DROP TABLE interval_games;
@@ -0,0 +1,5 @@
+CREATE TABLE interval_games (
+  id INTEGER NOT NULL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  finished_at TIMESTAMP
+);
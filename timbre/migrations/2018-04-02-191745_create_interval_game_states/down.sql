@@ -0,0 +1 @@
+DROP TABLE interval_game_states;
@@ -0,0 +1,11 @@
+CREATE TABLE interval_game_states (
+  id INTEGER NOT NULL PRIMARY KEY,
+  exercise INTEGER NOT NULL DEFAULT 1,
+  question TEXT NOT NULL DEFAULT '',
+  questions TEXT NOT NULL DEFAULT '',
+  right_count INTEGER NOT NULL DEFAULT 0,
+  total_count INTEGER NOT NULL DEFAULT 0,
+  game_id INTEGER NOT NULL REFERENCES interval_games(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX interval_game_states_game_id_idx ON interval_game_states(game_id);
@@ -0,0 +1,11 @@
+CREATE TABLE chord_game_states (
+  id INTEGER NOT NULL PRIMARY KEY,
+  exercise INTEGER NOT NULL DEFAULT 1,
+  chord TEXT NOT NULL DEFAULT '',
+  chords TEXT NOT NULL DEFAULT '',
+  right_count INTEGER NOT NULL DEFAULT 0,
+  total_count INTEGER NOT NULL DEFAULT 0,
+  game_id INTEGER NOT NULL REFERENCES chord_games(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX chord_game_states_game_id_idx ON chord_game_states(game_id);
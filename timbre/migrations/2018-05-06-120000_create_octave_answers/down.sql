@@ -0,0 +1 @@
+DROP TABLE octave_answers;
@@ -0,0 +1,13 @@
+CREATE TABLE octave_answers (
+  id INTEGER NOT NULL PRIMARY KEY,
+  game_id INTEGER NOT NULL REFERENCES octave_games(id) ON DELETE CASCADE,
+  exercise INTEGER NOT NULL,
+  note TEXT NOT NULL,
+  answer TEXT NOT NULL,
+  correct BOOLEAN NOT NULL,
+  response_ms INTEGER NOT NULL,
+  repeats INTEGER NOT NULL DEFAULT 0,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX octave_answers_game_id_idx ON octave_answers(game_id);
@@ -0,0 +1,7 @@
+CREATE TABLE octave_memory (
+  tonality TEXT NOT NULL,
+  note TEXT NOT NULL,
+  level INTEGER NOT NULL DEFAULT 1,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (tonality, note)
+);
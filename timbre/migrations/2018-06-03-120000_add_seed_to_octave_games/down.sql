@@ -0,0 +1,12 @@
+CREATE TABLE octave_games_backup (
+  id INTEGER NOT NULL PRIMARY KEY,
+  tonality TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  finished_at TIMESTAMP
+);
+
+INSERT INTO octave_games_backup
+  SELECT id, tonality, created_at, finished_at FROM octave_games;
+
+DROP TABLE octave_games;
+ALTER TABLE octave_games_backup RENAME TO octave_games;
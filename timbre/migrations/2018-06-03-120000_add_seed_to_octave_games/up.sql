@@ -0,0 +1 @@
+ALTER TABLE octave_games ADD COLUMN seed BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,19 @@
+CREATE TABLE octave_game_states_backup (
+  id INTEGER NOT NULL PRIMARY KEY,
+  tonality TEXT NOT NULL,
+  exercise INTEGER NOT NULL DEFAULT 1,
+  note TEXT NOT NULL DEFAULT '',
+  notes TEXT NOT NULL DEFAULT '',
+  right_count INTEGER NOT NULL DEFAULT 0,
+  total_count INTEGER NOT NULL DEFAULT 0,
+  game_id INTEGER NOT NULL REFERENCES octave_games(id) ON DELETE CASCADE
+);
+
+INSERT INTO octave_game_states_backup
+  SELECT id, tonality, exercise, note, notes, right_count, total_count, game_id
+  FROM octave_game_states;
+
+DROP TABLE octave_game_states;
+ALTER TABLE octave_game_states_backup RENAME TO octave_game_states;
+
+CREATE UNIQUE INDEX octave_game_states_game_id_idx ON octave_game_states(game_id);
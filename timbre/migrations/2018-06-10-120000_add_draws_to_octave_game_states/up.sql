@@ -0,0 +1 @@
+ALTER TABLE octave_game_states ADD COLUMN draws BIGINT NOT NULL DEFAULT 0;
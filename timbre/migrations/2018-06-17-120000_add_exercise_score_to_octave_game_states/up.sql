@@ -0,0 +1,6 @@
+ALTER TABLE octave_game_states ADD COLUMN exercise_right_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE octave_game_states ADD COLUMN exercise_total_count INTEGER NOT NULL DEFAULT 0;
+
+-- The games saved before only know the score of the whole game
+UPDATE octave_game_states
+  SET exercise_right_count = right_count, exercise_total_count = total_count;
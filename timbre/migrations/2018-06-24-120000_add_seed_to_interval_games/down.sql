@@ -0,0 +1,30 @@
+CREATE TABLE interval_game_states_backup (
+  id INTEGER NOT NULL PRIMARY KEY,
+  exercise INTEGER NOT NULL DEFAULT 1,
+  question TEXT NOT NULL DEFAULT '',
+  questions TEXT NOT NULL DEFAULT '',
+  right_count INTEGER NOT NULL DEFAULT 0,
+  total_count INTEGER NOT NULL DEFAULT 0,
+  game_id INTEGER NOT NULL REFERENCES interval_games(id) ON DELETE CASCADE
+);
+
+INSERT INTO interval_game_states_backup
+  SELECT id, exercise, question, questions, right_count, total_count, game_id
+  FROM interval_game_states;
+
+DROP TABLE interval_game_states;
+ALTER TABLE interval_game_states_backup RENAME TO interval_game_states;
+
+CREATE UNIQUE INDEX interval_game_states_game_id_idx ON interval_game_states(game_id);
+
+CREATE TABLE interval_games_backup (
+  id INTEGER NOT NULL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  finished_at TIMESTAMP
+);
+
+INSERT INTO interval_games_backup
+  SELECT id, created_at, finished_at FROM interval_games;
+
+DROP TABLE interval_games;
+ALTER TABLE interval_games_backup RENAME TO interval_games;
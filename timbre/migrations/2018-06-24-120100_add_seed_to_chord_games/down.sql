@@ -0,0 +1,30 @@
+CREATE TABLE chord_game_states_backup (
+  id INTEGER NOT NULL PRIMARY KEY,
+  exercise INTEGER NOT NULL DEFAULT 1,
+  chord TEXT NOT NULL DEFAULT '',
+  chords TEXT NOT NULL DEFAULT '',
+  right_count INTEGER NOT NULL DEFAULT 0,
+  total_count INTEGER NOT NULL DEFAULT 0,
+  game_id INTEGER NOT NULL REFERENCES chord_games(id) ON DELETE CASCADE
+);
+
+INSERT INTO chord_game_states_backup
+  SELECT id, exercise, chord, chords, right_count, total_count, game_id
+  FROM chord_game_states;
+
+DROP TABLE chord_game_states;
+ALTER TABLE chord_game_states_backup RENAME TO chord_game_states;
+
+CREATE UNIQUE INDEX chord_game_states_game_id_idx ON chord_game_states(game_id);
+
+CREATE TABLE chord_games_backup (
+  id INTEGER NOT NULL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  finished_at TIMESTAMP
+);
+
+INSERT INTO chord_games_backup
+  SELECT id, created_at, finished_at FROM chord_games;
+
+DROP TABLE chord_games;
+ALTER TABLE chord_games_backup RENAME TO chord_games;
@@ -0,0 +1,2 @@
+ALTER TABLE chord_games ADD COLUMN seed BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE chord_game_states ADD COLUMN draws BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::{fmt, str};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de;
+use toml;
+
+use error::Error;
+use pack;
+use sampler;
+use xdg_dirs;
+
+lazy_static! {
+    static ref FILE_PATH: PathBuf = xdg_dirs::CONFIG.join("config.toml");
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default = "default_sounds")]
+    pub sounds: Sounds,
+    // Sample archives or directories to sync from, tried in order
+    #[serde(default = "default_sources")]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub instrument: Instrument,
+    // Ask the weak notes of the octaves game more often
+    // until they are known
+    #[serde(default)]
+    pub adaptive: bool,
+    // Every game starts with this seed, so a whole class
+    // can take the same quiz
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    // Instruments chosen for the games by their names,
+    // e.g. `octaves = "guitar"`
+    #[serde(default)]
+    pub instruments: BTreeMap<String, Instrument>,
+}
+
+// Where the played sounds come from
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sounds {
+    // Recordings downloaded by the sampler
+    Samples,
+    // Built-in synthesizer, needs no download
+    Synth,
+}
+
+// The app played the samples before there was a synthesizer
+impl Default for Sounds {
+    fn default() -> Sounds {
+        Sounds::Samples
+    }
+}
+
+impl Sounds {
+    // The samples if any are installed, the synthesizer otherwise,
+    // so a fresh install plays right away
+    fn available(samples: &Path) -> Sounds {
+        if pack::installed(samples).is_empty() {
+            Sounds::Synth
+        } else {
+            Sounds::Samples
+        }
+    }
+}
+
+// Sample pack to play with
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instrument {
+    // The first installed pack
+    Default,
+    // Another pack on every question
+    Random,
+    // Pack by its directory name
+    Pack(String),
+}
+
+impl Default for Instrument {
+    fn default() -> Instrument {
+        Instrument::Default
+    }
+}
+
+impl str::FromStr for Instrument {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Instrument, ()> {
+        match s {
+            "" => Err(()),
+            "default" => Ok(Instrument::Default),
+            "random" => Ok(Instrument::Random),
+            _ => Ok(Instrument::Pack(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instrument::Default => write!(f, "default"),
+            Instrument::Random => write!(f, "random"),
+            Instrument::Pack(ref name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl Serialize for Instrument {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Instrument {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Instrument, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse::<Instrument>()
+            .map_err(|_| de::Error::custom("empty instrument name"))
+    }
+}
+
+impl Config {
+    fn new() -> Config {
+        Config {
+            sounds: default_sounds(),
+            sources: default_sources(),
+            instrument: Instrument::default(),
+            adaptive: false,
+            seed: None,
+            instruments: BTreeMap::new(),
+        }
+    }
+
+    // Instrument chosen for the game, falls back to the common one
+    pub fn instrument_for(&self, game: &str) -> Instrument {
+        self.instruments
+            .get(game)
+            .cloned()
+            .unwrap_or_else(|| self.instrument.clone())
+    }
+
+    // The defaults if there is no config yet
+    pub fn load() -> Result<Config, Error> {
+        Config::load_from(&FILE_PATH)
+    }
+
+    fn load_from(path: &Path) -> Result<Config, Error> {
+        let mut serialized = String::new();
+
+        match fs::File::open(path) {
+            Ok(mut file) => file.read_to_string(&mut serialized)?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(toml::from_str(&serialized)?)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let serialized = toml::to_string(&self)?;
+
+        let mut file = fs::File::create(FILE_PATH.clone())?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn default_sounds() -> Sounds {
+    Sounds::available(&xdg_dirs::SAMPLES)
+}
+
+fn default_sources() -> Vec<String> {
+    vec![sampler::DEFAULT_SOURCE.to_owned()]
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn parse_instruments() {
+        let config = toml::from_str::<Config>(
+            r#"
+            instrument = "random"
+
+            [instruments]
+            octaves = "guitar"
+            "#,
+        ).unwrap();
+
+        assert_eq!(config.sounds, default_sounds());
+        assert_eq!(config.instrument_for("octaves"), Instrument::Pack("guitar".to_owned()));
+        assert_eq!(config.instrument_for("chords"), Instrument::Random);
+
+        let serialized = toml::to_string(&config).unwrap();
+        assert!(serialized.contains("instrument = \"random\""));
+        assert!(serialized.contains("octaves = \"guitar\""));
+    }
+
+    #[test]
+    fn load_config_file() {
+        let tmp = TempDir::new("config").unwrap();
+        let path = tmp.path().join("config.toml");
+        assert_eq!(Config::load_from(&path).unwrap().sources, default_sources());
+
+        fs::File::create(&path)
+            .unwrap()
+            .write_all(b"sounds = \"synth\"\nadaptive = true\n")
+            .unwrap();
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.sounds, Sounds::Synth);
+        assert!(config.adaptive);
+
+        // A typo isn't silently replaced by the defaults
+        fs::File::create(&path)
+            .unwrap()
+            .write_all(b"sounds = \"synht\"\n")
+            .unwrap();
+        match Config::load_from(&path) {
+            Err(Error::ParseToml(_)) => (),
+            res => panic!("Expected a parse error, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn sounds_by_installed_samples() {
+        let tmp = TempDir::new("samples").unwrap();
+        assert_eq!(Sounds::available(tmp.path()), Sounds::Synth);
+
+        // Synced before there were several packs
+        fs::create_dir(tmp.path().join("notes")).unwrap();
+        assert_eq!(Sounds::available(tmp.path()), Sounds::Samples);
+    }
+}
@@ -0,0 +1,131 @@
+use diesel::Connection;
+use diesel::sqlite::SqliteConnection;
+use diesel_migrations::{self, MigrationConnection};
+use std::path::Path;
+use std::rc::Rc;
+
+use error::Error;
+use xdg_dirs;
+
+// Version of the latest migration, the binary can't work
+// with a database migrated past it
+pub const SCHEMA_VERSION: &'static str = "20180624120100";
+
+embed_migrations!("migrations");
+
+// Connection shared by the games and the frontend,
+// the clones use the same connection
+#[derive(Clone)]
+pub struct Database {
+    conn: Rc<SqliteConnection>,
+}
+
+impl Database {
+    // Open the database and bring its schema up to date
+    pub fn open(path: &Path) -> Result<Database, Error> {
+        let conn = SqliteConnection::establish(&path.to_string_lossy())?;
+        setup(&conn)?;
+
+        Ok(Database {
+            conn: Rc::new(conn),
+        })
+    }
+
+    // The database in the data directory of the user
+    pub fn open_default() -> Result<Database, Error> {
+        Database::open(&xdg_dirs::DATA.join("timbre.db"))
+    }
+
+    // Empty database living as long as the connection, e.g. for tests
+    pub fn in_memory() -> Result<Database, Error> {
+        Database::open(Path::new(":memory:"))
+    }
+
+    pub fn connection(&self) -> &SqliteConnection {
+        &self.conn
+    }
+}
+
+// Apply the pending migrations, e.g. create the tables on the first run
+pub fn setup(conn: &SqliteConnection) -> Result<(), Error> {
+    diesel_migrations::setup_database(conn)?;
+
+    if let Some(version) = conn.latest_run_migration_version()? {
+        if version.as_str() > SCHEMA_VERSION {
+            return Err(Error::NewerSchema(version));
+        }
+    }
+
+    embedded_migrations::run(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::prelude::*;
+    use schema::octave_games;
+    use std::fs;
+    use tempdir::TempDir;
+
+    fn connection() -> SqliteConnection {
+        SqliteConnection::establish(":memory:").unwrap()
+    }
+
+    #[test]
+    fn create_tables_on_first_run() {
+        let conn = connection();
+        assert!(octave_games::table.count().get_result::<i64>(&conn).is_err());
+
+        setup(&conn).unwrap();
+        assert_eq!(octave_games::table.count().get_result::<i64>(&conn), Ok(0));
+
+        // Nothing is pending the next time
+        setup(&conn).unwrap();
+        assert_eq!(conn.latest_run_migration_version(), Ok(Some(SCHEMA_VERSION.to_owned())));
+    }
+
+    #[test]
+    fn reopen_database_file() {
+        let tmp = TempDir::new("db").unwrap();
+        let path = tmp.path().join("timbre.db");
+
+        let db = Database::open(&path).unwrap();
+        diesel::insert_into(octave_games::table)
+            .values(octave_games::tonality.eq("Cmaj"))
+            .execute(db.connection())
+            .unwrap();
+        drop(db);
+
+        let db = Database::open(&path).unwrap();
+        let count = octave_games::table.count().get_result::<i64>(db.connection());
+        assert_eq!(count, Ok(1));
+    }
+
+    #[test]
+    fn reject_newer_database() {
+        let conn = connection();
+        setup(&conn).unwrap();
+        conn.insert_new_migration("20990101000000").unwrap();
+
+        match setup(&conn) {
+            Err(Error::NewerSchema(version)) => assert_eq!(version, "20990101000000"),
+            res => panic!("Expected a newer schema, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn schema_version_is_latest_migration() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+        let latest = fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| {
+                let name = entry.unwrap().file_name().into_string().unwrap();
+                name.split('_').next().map(|v| v.replace("-", ""))
+            })
+            .max()
+            .unwrap();
+
+        assert_eq!(latest, SCHEMA_VERSION);
+    }
+}
@@ -0,0 +1,126 @@
+use diesel;
+use diesel_migrations::RunMigrationsError;
+use reqwest;
+use std::{error, fmt, io};
+use toml;
+
+use games::octaves::note::Note;
+
+// Everything that may go wrong in the library
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    // Can't open the database
+    Connection(diesel::ConnectionError),
+    Database(diesel::result::Error),
+    Migration(RunMigrationsError),
+    // The database was migrated by a newer version of the app
+    NewerSchema(String),
+    Http(reqwest::Error),
+    ParseToml(toml::de::Error),
+    WriteToml(toml::ser::Error),
+    // The action needs a running game
+    NoGame,
+    // The audio backend can't play a note of the exercise
+    OutOfRange(Note),
+    // The manifest of a sample pack is wrong
+    InvalidPack(String),
+    // An exercise definition of the teacher is wrong
+    InvalidExercise(String),
+    // The samples can't be synced, e.g. a checksum mismatch
+    Sync(String),
+    // A sample can't be read for rendering, e.g. a broken Ogg file
+    Decode(String),
+    SyncCancelled,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::Connection(ref e) => write!(f, "Can't open the database: {}", e),
+            Error::Database(ref e) => write!(f, "Database error: {}", e),
+            Error::Migration(ref e) => write!(f, "Can't update the database: {}", e),
+            Error::NewerSchema(ref version) => write!(
+                f,
+                "The database was created by a newer version of Timbre (schema {}), \
+                 please update the app",
+                version
+            ),
+            Error::Http(ref e) => write!(f, "{}", e),
+            Error::ParseToml(ref e) => write!(f, "{}", e),
+            Error::WriteToml(ref e) => write!(f, "{}", e),
+            Error::NoGame => write!(f, "No game is running"),
+            Error::OutOfRange(note) => write!(f, "{} is out of the range of the sample pack", note),
+            Error::InvalidPack(ref msg) => write!(f, "Invalid sample pack: {}", msg),
+            Error::InvalidExercise(ref msg) => write!(f, "Invalid exercise {}", msg),
+            Error::Sync(ref msg) => write!(f, "{}", msg),
+            Error::Decode(ref msg) => write!(f, "Can't decode the sample {}", msg),
+            Error::SyncCancelled => write!(f, "Synchronization cancelled"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Io(_) => "I/O error",
+            Error::Connection(_) => "database connection error",
+            Error::Database(_) => "database error",
+            Error::Migration(_) => "migration error",
+            Error::NewerSchema(_) => "database schema is newer than the app",
+            Error::Http(_) => "HTTP error",
+            Error::ParseToml(_) => "TOML parse error",
+            Error::WriteToml(_) => "TOML write error",
+            Error::NoGame => "no game is running",
+            Error::OutOfRange(_) => "note is out of range",
+            Error::InvalidPack(_) => "invalid sample pack",
+            Error::InvalidExercise(_) => "invalid exercise",
+            Error::Sync(_) => "sync error",
+            Error::Decode(_) => "decode error",
+            Error::SyncCancelled => "sync cancelled",
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<diesel::ConnectionError> for Error {
+    fn from(e: diesel::ConnectionError) -> Error {
+        Error::Connection(e)
+    }
+}
+
+impl From<diesel::result::Error> for Error {
+    fn from(e: diesel::result::Error) -> Error {
+        Error::Database(e)
+    }
+}
+
+impl From<RunMigrationsError> for Error {
+    fn from(e: RunMigrationsError) -> Error {
+        Error::Migration(e)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Error {
+        Error::Http(e)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Error {
+        Error::ParseToml(e)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(e: toml::ser::Error) -> Error {
+        Error::WriteToml(e)
+    }
+}
@@ -0,0 +1,270 @@
+use std::{fmt, str};
+
+use games::octaves::note::Note;
+
+lazy_static! {
+    pub static ref QUALITIES: Vec<Quality> = {
+        let mut v = Vec::new();
+
+        v.push(Quality::Major);
+        v.push(Quality::Minor);
+        v.push(Quality::Diminished);
+        v.push(Quality::Augmented);
+        v.push(Quality::Dominant7);
+        v.push(Quality::Major7);
+        v.push(Quality::Minor7);
+        v.push(Quality::HalfDiminished7);
+        v.push(Quality::Diminished7);
+
+        v
+    };
+
+    pub static ref INVERSIONS: Vec<Inversion> = {
+        let mut v = Vec::new();
+
+        v.push(Inversion::Root);
+        v.push(Inversion::First);
+        v.push(Inversion::Second);
+        v.push(Inversion::Third);
+
+        v
+    };
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Quality {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Dominant7,
+    Major7,
+    Minor7,
+    HalfDiminished7,
+    Diminished7,
+}
+
+impl Quality {
+    // Letter names and semitones of every chord tone above the root
+    fn formula(&self) -> &'static [(i8, i8)] {
+        match *self {
+            Quality::Major => &[(2, 4), (4, 7)],
+            Quality::Minor => &[(2, 3), (4, 7)],
+            Quality::Diminished => &[(2, 3), (4, 6)],
+            Quality::Augmented => &[(2, 4), (4, 8)],
+            Quality::Dominant7 => &[(2, 4), (4, 7), (6, 10)],
+            Quality::Major7 => &[(2, 4), (4, 7), (6, 11)],
+            Quality::Minor7 => &[(2, 3), (4, 7), (6, 10)],
+            Quality::HalfDiminished7 => &[(2, 3), (4, 6), (6, 10)],
+            Quality::Diminished7 => &[(2, 3), (4, 6), (6, 9)],
+        }
+    }
+
+    pub fn is_seventh(&self) -> bool {
+        self.formula().len() == 3
+    }
+
+    // A triad has two inversions and a seventh chord has three
+    pub fn has_inversion(&self, inversion: Inversion) -> bool {
+        inversion as usize <= self.formula().len()
+    }
+}
+
+impl str::FromStr for Quality {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Quality, ()> {
+        match s {
+            "maj" => Ok(Quality::Major),
+            "min" => Ok(Quality::Minor),
+            "dim" => Ok(Quality::Diminished),
+            "aug" => Ok(Quality::Augmented),
+            "dom7" => Ok(Quality::Dominant7),
+            "maj7" => Ok(Quality::Major7),
+            "m7" => Ok(Quality::Minor7),
+            "m7b5" => Ok(Quality::HalfDiminished7),
+            "dim7" => Ok(Quality::Diminished7),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Quality::Major => "maj",
+            Quality::Minor => "min",
+            Quality::Diminished => "dim",
+            Quality::Augmented => "aug",
+            Quality::Dominant7 => "dom7",
+            Quality::Major7 => "maj7",
+            Quality::Minor7 => "m7",
+            Quality::HalfDiminished7 => "m7b5",
+            Quality::Diminished7 => "dim7",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Inversion {
+    Root = 0,
+    First = 1,
+    Second = 2,
+    Third = 3,
+}
+
+impl str::FromStr for Inversion {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Inversion, ()> {
+        match s {
+            "0" => Ok(Inversion::Root),
+            "1" => Ok(Inversion::First),
+            "2" => Ok(Inversion::Second),
+            "3" => Ok(Inversion::Third),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Inversion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", *self as u8)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Chord {
+    pub root: Note,
+    pub quality: Quality,
+    pub inversion: Inversion,
+}
+
+impl Chord {
+    // Returns `None` if the inversion doesn't exist for the quality
+    // or the chord can't be spelled within the octave range
+    pub fn new(root: Note, quality: Quality, inversion: Inversion) -> Option<Chord> {
+        let chord = Chord {
+            root,
+            quality,
+            inversion,
+        };
+
+        chord.voicing().map(|_| chord)
+    }
+
+    // Chord tones from the lowest one
+    pub fn notes(&self) -> Vec<Note> {
+        self.voicing().expect("Invalid chord")
+    }
+
+    fn voicing(&self) -> Option<Vec<Note>> {
+        if !self.quality.has_inversion(self.inversion) {
+            return None;
+        }
+
+        let mut notes = vec![self.root];
+        for &(steps, semitones) in self.quality.formula().iter() {
+            notes.push(self.root.transpose(steps, semitones)?);
+        }
+
+        let inversion = self.inversion as usize;
+
+        for i in 0..inversion {
+            notes[i] = notes[i].transpose(7, 12)?;
+        }
+        notes.rotate_left(inversion);
+
+        Some(notes)
+    }
+}
+
+impl str::FromStr for Chord {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Chord, ()> {
+        let colon = s.find(':').ok_or(())?;
+        let slash = s.find('/').ok_or(())?;
+        if slash < colon {
+            return Err(());
+        }
+
+        let root = s[..colon].parse::<Note>()?;
+        let quality = s[colon + 1..slash].parse::<Quality>()?;
+        let inversion = s[slash + 1..].parse::<Inversion>()?;
+
+        Chord::new(root, quality, inversion).ok_or(())
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}/{}", self.root, self.quality, self.inversion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(s: &str) -> Vec<Note> {
+        s.split_whitespace().map(|n| n.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn chord_notes() {
+        let root = "C4".parse::<Note>().unwrap();
+
+        let cases = [
+            (Quality::Major, "C4 E4 G4"),
+            (Quality::Minor, "C4 Eb4 G4"),
+            (Quality::Diminished, "C4 Eb4 Gb4"),
+            (Quality::Augmented, "C4 E4 G#4"),
+            (Quality::Dominant7, "C4 E4 G4 Bb4"),
+            (Quality::Major7, "C4 E4 G4 B4"),
+            (Quality::Minor7, "C4 Eb4 G4 Bb4"),
+            (Quality::HalfDiminished7, "C4 Eb4 Gb4 Bb4"),
+        ];
+
+        for &(quality, expected) in cases.iter() {
+            let chord = Chord::new(root, quality, Inversion::Root).unwrap();
+            assert_eq!(chord.notes(), notes(expected), "{}", quality);
+        }
+
+        // Cdim7 needs B double flat
+        assert!(Chord::new(root, Quality::Diminished7, Inversion::Root).is_none());
+
+        let root = "B3".parse::<Note>().unwrap();
+        let chord = Chord::new(root, Quality::Diminished7, Inversion::Root).unwrap();
+        assert_eq!(chord.notes(), notes("B3 D4 F4 Ab4"));
+    }
+
+    #[test]
+    fn chord_inversions() {
+        let root = "D4".parse::<Note>().unwrap();
+
+        let chord = Chord::new(root, Quality::Major, Inversion::First).unwrap();
+        assert_eq!(chord.notes(), notes("F#4 A4 D5"));
+
+        let chord = Chord::new(root, Quality::Major, Inversion::Second).unwrap();
+        assert_eq!(chord.notes(), notes("A4 D5 F#5"));
+
+        let chord = Chord::new(root, Quality::Dominant7, Inversion::Third).unwrap();
+        assert_eq!(chord.notes(), notes("C5 D5 F#5 A5"));
+
+        assert!(Chord::new(root, Quality::Major, Inversion::Third).is_none());
+    }
+
+    #[test]
+    fn chord_display() {
+        let root = "Bb3".parse::<Note>().unwrap();
+        let chord = Chord::new(root, Quality::HalfDiminished7, Inversion::Second).unwrap();
+
+        assert_eq!(chord.to_string(), "Bb3:m7b5/2");
+        assert_eq!("Bb3:m7b5/2".parse::<Chord>(), Ok(chord));
+        assert!("Bb3:maj/3".parse::<Chord>().is_err());
+        assert!("Bb3/maj:0".parse::<Chord>().is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+
+pub use self::state::State;
+use self::chord::{Chord, Inversion, Quality, INVERSIONS, QUALITIES};
+use self::models::{GameState, NewGame, NewGameState};
+use games::octaves::note::Note;
+use games::{Game, Score, Session, SharedSession};
+use player::{AudioBackend, EarsBackend};
+use schema::{chord_game_states, chord_games};
+
+pub mod chord;
+pub mod models;
+mod state;
+
+lazy_static! {
+    static ref EXERCISES: Vec<Exercise> = {
+        let mut v = Vec::new();
+
+        let mut qualities = Vec::new();
+        qualities.push(Quality::Major);
+        qualities.push(Quality::Minor);
+        v.push(Exercise {
+            num: 1,
+            qualities,
+            inversions: vec![Inversion::Root],
+            name_inversion: false,
+        });
+
+        let mut qualities = Vec::new();
+        qualities.push(Quality::Major);
+        qualities.push(Quality::Minor);
+        qualities.push(Quality::Diminished);
+        qualities.push(Quality::Augmented);
+        v.push(Exercise {
+            num: 2,
+            qualities,
+            inversions: vec![Inversion::Root],
+            name_inversion: false,
+        });
+
+        let mut qualities = Vec::new();
+        qualities.push(Quality::Dominant7);
+        qualities.push(Quality::Major7);
+        qualities.push(Quality::Minor7);
+        qualities.push(Quality::HalfDiminished7);
+        qualities.push(Quality::Diminished7);
+        v.push(Exercise {
+            num: 3,
+            qualities,
+            inversions: vec![Inversion::Root],
+            name_inversion: false,
+        });
+
+        let mut qualities = Vec::new();
+        qualities.push(Quality::Major);
+        qualities.push(Quality::Minor);
+        qualities.push(Quality::Diminished);
+        qualities.push(Quality::Augmented);
+        v.push(Exercise {
+            num: 4,
+            qualities,
+            inversions: INVERSIONS.clone(),
+            name_inversion: true,
+        });
+
+        v.push(Exercise {
+            num: 5,
+            qualities: QUALITIES.clone(),
+            inversions: INVERSIONS.clone(),
+            name_inversion: true,
+        });
+
+        v
+    };
+}
+
+#[derive(Clone, Debug)]
+pub struct Exercise {
+    pub num: u8,
+    qualities: Vec<Quality>,
+    inversions: Vec<Inversion>,
+    // Whether the answer has to name the inversion too
+    pub name_inversion: bool,
+}
+
+pub type Controller<B = EarsBackend> = Session<State, B>;
+
+pub type SharedController<B = EarsBackend> = SharedSession<State, B>;
+
+impl Game for State {
+    type Settings = ();
+    type Exercise = Exercise;
+    type Question = Chord;
+    type Answer = (Quality, Inversion);
+
+    const NAME: &'static str = "chords";
+
+    fn create(_: (), seed: u64) -> State {
+        let exercise = EXERCISES.first().cloned().unwrap();
+        State::new(exercise, seed)
+    }
+
+    fn question(&self) -> Option<Chord> {
+        self.chord
+    }
+
+    fn next_question(&mut self) -> Option<Chord> {
+        self.next_chord()
+    }
+
+    fn check_answer(&self, chord: Chord, answer: &(Quality, Inversion)) -> bool {
+        let (quality, inversion) = *answer;
+        chord.quality == quality && (!self.exercise.name_inversion || chord.inversion == inversion)
+    }
+
+    fn next_exercise(&mut self) -> Option<&'static Exercise> {
+        State::next_exercise(self)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.chords.is_empty()
+    }
+
+    fn notes(&self) -> Vec<Note> {
+        self.chords.iter().flat_map(|c| c.notes()).collect()
+    }
+
+    fn score(&self) -> &Score {
+        &self.score
+    }
+
+    fn score_mut(&mut self) -> &mut Score {
+        &mut self.score
+    }
+
+    fn play(&self, chord: Chord, player: &AudioBackend) {
+        player.play_chord(&chord.notes());
+    }
+
+    fn insert(&self, conn: &SqliteConnection) -> QueryResult<()> {
+        let new_game = NewGame {
+            seed: self.seed as i64,
+        };
+
+        diesel::insert_into(chord_games::table)
+            .values(&new_game)
+            .execute(conn)
+            .map(|_| ())
+    }
+
+    fn load(conn: &SqliteConnection) -> QueryResult<Option<State>> {
+        let game = match current_game(conn)? {
+            Some(game) => game,
+            None => return Ok(None),
+        };
+
+        let game_state = GameState::belonging_to(&game)
+            .first::<GameState>(conn)
+            .optional()?;
+
+        let game_state = match game_state {
+            Some(game_state) => game_state,
+            None => return Ok(None),
+        };
+
+        match State::load(&game_state, game.seed as u64) {
+            Some(state) => Ok(Some(state)),
+            None => {
+                // The exercise is gone or the state is broken,
+                // a new game starts instead
+                Self::finish(conn)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn save(&self, conn: &SqliteConnection) -> QueryResult<()> {
+        let game = match current_game(conn)? {
+            Some(game) => game,
+            None => return Ok(()),
+        };
+
+        let game_state = GameState::belonging_to(&game)
+            .first::<GameState>(conn)
+            .optional()?;
+        match game_state {
+            Some(current_state) => {
+                let changeset = self.changeset();
+
+                diesel::update(&current_state)
+                    .set(&changeset)
+                    .execute(conn)?;
+            }
+            None => {
+                let changeset = self.changeset();
+                let new_state = NewGameState {
+                    exercise: changeset.exercise,
+                    chord: changeset.chord,
+                    chords: changeset.chords,
+                    right_count: changeset.right_count,
+                    total_count: changeset.total_count,
+                    game_id: game.id,
+                    draws: changeset.draws,
+                };
+
+                diesel::insert_into(chord_game_states::table)
+                    .values(&new_state)
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(conn: &SqliteConnection) -> QueryResult<()> {
+        if let Some(game) = current_game(conn)? {
+            diesel::update(chord_games::table)
+                .set(chord_games::finished_at.eq(Utc::now().naive_utc()))
+                .filter(chord_games::id.eq(game.id))
+                .execute(conn)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn current_game(conn: &SqliteConnection) -> QueryResult<Option<models::Game>> {
+    chord_games::table
+        .filter(chord_games::finished_at.is_null())
+        .order(chord_games::created_at.desc())
+        .first::<models::Game>(conn)
+        .optional()
+}
@@ -0,0 +1,56 @@
+use chrono::NaiveDateTime;
+use schema::{chord_game_states, chord_games};
+
+#[derive(Identifiable, Queryable)]
+#[table_name = "chord_games"]
+pub struct Game {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    // Bits of the `u64` seed of the questions
+    pub seed: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "chord_games"]
+pub struct NewGame {
+    pub seed: i64,
+}
+
+#[derive(Associations, Identifiable, Queryable)]
+#[belongs_to(Game)]
+#[table_name = "chord_game_states"]
+pub struct GameState {
+    pub id: i32,
+    pub exercise: i32,
+    pub chord: String,
+    pub chords: String,
+    pub right_count: i32,
+    pub total_count: i32,
+    pub game_id: i32,
+    // Random numbers drawn for the questions since the seed
+    pub draws: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "chord_game_states"]
+pub struct NewGameState {
+    pub exercise: i32,
+    pub chord: String,
+    pub chords: String,
+    pub right_count: i32,
+    pub total_count: i32,
+    pub game_id: i32,
+    pub draws: i64,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "chord_game_states"]
+pub struct GameStateChangeset {
+    pub exercise: i32,
+    pub chord: String,
+    pub chords: String,
+    pub right_count: i32,
+    pub total_count: i32,
+    pub draws: i64,
+}
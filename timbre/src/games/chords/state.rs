@@ -0,0 +1,215 @@
+use rand::Rng;
+use games::chords::{Exercise, EXERCISES};
+
+use super::chord::Chord;
+use games::chords::models::{GameState, GameStateChangeset};
+use games::replay::ReplayRng;
+use games::Score;
+use games::octaves::note::{Note, Octave, Pitch};
+
+// Each chord is asked this many times in every inversion
+const REPEATS: usize = 2;
+
+const ROOTS: [Pitch; 12] = [
+    Pitch::C,
+    Pitch::Dflat,
+    Pitch::D,
+    Pitch::Eflat,
+    Pitch::E,
+    Pitch::F,
+    Pitch::Fsharp,
+    Pitch::G,
+    Pitch::Aflat,
+    Pitch::A,
+    Pitch::Bflat,
+    Pitch::B,
+];
+
+#[derive(Debug)]
+pub struct State {
+    // Current exercise
+    pub exercise: Exercise,
+    // Current chord
+    pub chord: Option<Chord>,
+    // Chords to play
+    pub chords: Vec<Chord>,
+    // Answers count
+    pub score: Score,
+    // The same seed gives the same questions
+    pub seed: u64,
+    rng: ReplayRng,
+}
+
+impl State {
+    pub fn new(exercise: Exercise, seed: u64) -> State {
+        let mut state = State {
+            exercise,
+            chord: None,
+            chords: vec![],
+            score: Score::default(),
+            seed,
+            rng: ReplayRng::new(seed, 0),
+        };
+
+        state.generate_chords();
+        state
+    }
+
+    // A loaded game goes on with the same questions, the drawn numbers
+    // are replayed from the seed.
+    // Returns `None` if the exercise is gone or the state can't be read.
+    pub fn load(game_state: &GameState, seed: u64) -> Option<State> {
+        let exercise = EXERCISES
+            .iter()
+            .find(|&ex| ex.num == game_state.exercise as u8)
+            .cloned()?;
+        // Empty if the game was saved before the first question
+        let chord = match game_state.chord.as_str() {
+            "" => None,
+            chord => Some(chord.parse::<Chord>().ok()?),
+        };
+        let chords = game_state
+            .chords
+            .split(",")
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<Chord>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(State {
+            exercise,
+            chord,
+            chords,
+            score: Score {
+                right_count: game_state.right_count as u32,
+                total_count: game_state.total_count as u32,
+            },
+            seed,
+            rng: ReplayRng::new(seed, game_state.draws as u64),
+        })
+    }
+
+    pub fn changeset(&self) -> GameStateChangeset {
+        GameStateChangeset {
+            exercise: self.exercise.num as i32,
+            chord: self.chord.map_or("".to_owned(), |c| c.to_string()),
+            chords: self.chords
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            right_count: self.score.right_count as i32,
+            total_count: self.score.total_count as i32,
+            draws: self.rng.draws() as i64,
+        }
+    }
+
+    fn generate_chords(&mut self) {
+        let mut roots = ROOTS.to_vec();
+
+        for quality in self.exercise.qualities.iter() {
+            for inversion in self.exercise.inversions.iter() {
+                if !quality.has_inversion(*inversion) {
+                    continue;
+                }
+
+                for _ in 0..REPEATS {
+                    self.rng.shuffle(&mut roots);
+
+                    let chord = roots
+                        .iter()
+                        .filter_map(|&pitch| {
+                            let root = Note {
+                                octave: Octave::Small,
+                                pitch,
+                            };
+                            Chord::new(root, *quality, *inversion)
+                        })
+                        .next();
+
+                    if let Some(c) = chord {
+                        self.chords.push(c);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn next_chord(&mut self) -> Option<Chord> {
+        self.drop_chord();
+        self.chord = self.rng.choose(&self.chords).map(|c| *c);
+        self.chord
+    }
+
+    fn drop_chord(&mut self) {
+        let chord = self.chord;
+        if let Some(index) = self.chords.iter().position(|&c| Some(c) == chord) {
+            self.chords.remove(index);
+        }
+    }
+
+    pub fn next_exercise(&mut self) -> Option<&'static Exercise> {
+        let num = self.exercise.num + 1;
+        EXERCISES.iter().find(|&ex| ex.num == num).and_then(|ex| {
+            self.exercise = ex.clone();
+            self.generate_chords();
+
+            Some(ex)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::chord::{Inversion, Quality};
+
+    #[test]
+    fn generate_chords_first_ex() {
+        let exercise = EXERCISES.iter().nth(0).cloned().unwrap();
+        let state = State::new(exercise.clone(), 1);
+
+        assert_eq!(state.chords.len(), exercise.qualities.len() * REPEATS);
+
+        for c in state.chords.iter() {
+            assert!(exercise.qualities.contains(&c.quality));
+            assert_eq!(c.inversion, Inversion::Root);
+            assert_eq!(c.root.octave, Octave::Small);
+        }
+    }
+
+    #[test]
+    fn generate_chords_with_inversions() {
+        let exercise = EXERCISES.iter().last().cloned().unwrap();
+        let state = State::new(exercise, 1);
+
+        let triads = state
+            .chords
+            .iter()
+            .filter(|c| c.quality == Quality::Major)
+            .count();
+        assert_eq!(triads, 3 * REPEATS);
+
+        let sevenths = state
+            .chords
+            .iter()
+            .filter(|c| c.quality == Quality::Dominant7)
+            .count();
+        assert_eq!(sevenths, 4 * REPEATS);
+    }
+
+    #[test]
+    fn next_chord_first_ex() {
+        let exercise = EXERCISES.iter().nth(0).cloned().unwrap();
+        let mut state = State::new(exercise, 1);
+        let total = state.chords.len();
+
+        assert!(state.chord.is_none());
+
+        for _ in 0..total {
+            assert!(state.next_chord().is_some());
+        }
+
+        assert!(state.next_chord().is_none());
+        assert!(state.chords.is_empty());
+    }
+}
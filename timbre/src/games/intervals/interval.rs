@@ -0,0 +1,293 @@
+use std::{fmt, str};
+
+use games::octaves::note::Note;
+
+lazy_static! {
+    pub static ref INTERVALS: Vec<Interval> = {
+        let mut v = Vec::new();
+
+        v.push(Interval::MinorSecond);
+        v.push(Interval::MajorSecond);
+        v.push(Interval::MinorThird);
+        v.push(Interval::MajorThird);
+        v.push(Interval::PerfectFourth);
+        v.push(Interval::Tritone);
+        v.push(Interval::PerfectFifth);
+        v.push(Interval::MinorSixth);
+        v.push(Interval::MajorSixth);
+        v.push(Interval::MinorSeventh);
+        v.push(Interval::MajorSeventh);
+        v.push(Interval::PerfectOctave);
+
+        v
+    };
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Interval {
+    MinorSecond,
+    MajorSecond,
+    MinorThird,
+    MajorThird,
+    PerfectFourth,
+    Tritone,
+    PerfectFifth,
+    MinorSixth,
+    MajorSixth,
+    MinorSeventh,
+    MajorSeventh,
+    PerfectOctave,
+}
+
+impl Interval {
+    pub fn semitones(&self) -> i8 {
+        match *self {
+            Interval::MinorSecond => 1,
+            Interval::MajorSecond => 2,
+            Interval::MinorThird => 3,
+            Interval::MajorThird => 4,
+            Interval::PerfectFourth => 5,
+            Interval::Tritone => 6,
+            Interval::PerfectFifth => 7,
+            Interval::MinorSixth => 8,
+            Interval::MajorSixth => 9,
+            Interval::MinorSeventh => 10,
+            Interval::MajorSeventh => 11,
+            Interval::PerfectOctave => 12,
+        }
+    }
+
+    // Number of letter names between the notes,
+    // the tritone is spelled as an augmented fourth
+    pub fn steps(&self) -> i8 {
+        match *self {
+            Interval::MinorSecond | Interval::MajorSecond => 1,
+            Interval::MinorThird | Interval::MajorThird => 2,
+            Interval::PerfectFourth | Interval::Tritone => 3,
+            Interval::PerfectFifth => 4,
+            Interval::MinorSixth | Interval::MajorSixth => 5,
+            Interval::MinorSeventh | Interval::MajorSeventh => 6,
+            Interval::PerfectOctave => 7,
+        }
+    }
+
+    // Interval between the sounding notes in either order,
+    // whatever their spelling, e.g. C4 and D#4 or C4 and Eb4
+    pub fn between(a: Note, b: Note) -> Option<Interval> {
+        let semitones = a.semitones_to(b).abs();
+        INTERVALS.iter().find(|i| i.semitones() == semitones).cloned()
+    }
+
+    // The note the interval above, spelled by the letter names.
+    // Returns `None` out of the octave range.
+    pub fn above(&self, note: Note) -> Option<Note> {
+        note.transpose(self.steps(), self.semitones())
+    }
+
+    pub fn below(&self, note: Note) -> Option<Note> {
+        note.transpose(-self.steps(), -self.semitones())
+    }
+}
+
+impl str::FromStr for Interval {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Interval, ()> {
+        match s {
+            "m2" => Ok(Interval::MinorSecond),
+            "M2" => Ok(Interval::MajorSecond),
+            "m3" => Ok(Interval::MinorThird),
+            "M3" => Ok(Interval::MajorThird),
+            "P4" => Ok(Interval::PerfectFourth),
+            "TT" => Ok(Interval::Tritone),
+            "P5" => Ok(Interval::PerfectFifth),
+            "m6" => Ok(Interval::MinorSixth),
+            "M6" => Ok(Interval::MajorSixth),
+            "m7" => Ok(Interval::MinorSeventh),
+            "M7" => Ok(Interval::MajorSeventh),
+            "P8" => Ok(Interval::PerfectOctave),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Interval::MinorSecond => "m2",
+            Interval::MajorSecond => "M2",
+            Interval::MinorThird => "m3",
+            Interval::MajorThird => "M3",
+            Interval::PerfectFourth => "P4",
+            Interval::Tritone => "TT",
+            Interval::PerfectFifth => "P5",
+            Interval::MinorSixth => "m6",
+            Interval::MajorSixth => "M6",
+            Interval::MinorSeventh => "m7",
+            Interval::MajorSeventh => "M7",
+            Interval::PerfectOctave => "P8",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Direction {
+    // Melodically from the lower note
+    Ascending,
+    // Melodically from the upper note
+    Descending,
+    // Both notes at once
+    Harmonic,
+}
+
+impl Direction {
+    fn symbol(&self) -> char {
+        match *self {
+            Direction::Ascending => '+',
+            Direction::Descending => '-',
+            Direction::Harmonic => '=',
+        }
+    }
+}
+
+// Interval to be played from the root note
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Question {
+    pub root: Note,
+    pub interval: Interval,
+    pub direction: Direction,
+    other: Note,
+}
+
+impl Question {
+    pub fn new(root: Note, interval: Interval, direction: Direction) -> Option<Question> {
+        let other = match direction {
+            Direction::Descending => interval.below(root),
+            _ => interval.above(root),
+        }?;
+
+        Some(Question {
+            root,
+            interval,
+            direction,
+            other,
+        })
+    }
+
+    // Notes in the order they are played
+    pub fn notes(&self) -> [Note; 2] {
+        [self.root, self.other]
+    }
+}
+
+impl str::FromStr for Question {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Question, ()> {
+        let directions = [
+            Direction::Ascending,
+            Direction::Descending,
+            Direction::Harmonic,
+        ];
+
+        for direction in directions.iter() {
+            if let Some(idx) = s.find(direction.symbol()) {
+                let root = s[..idx].parse::<Note>()?;
+                let interval = s[idx + 1..].parse::<Interval>()?;
+
+                return Question::new(root, interval, *direction).ok_or(());
+            }
+        }
+
+        Err(())
+    }
+}
+
+impl fmt::Display for Question {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            self.root,
+            self.direction.symbol(),
+            self.interval
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(s: &str) -> Note {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn interval_semitones() {
+        let semitones = INTERVALS.iter().map(|i| i.semitones()).collect::<Vec<_>>();
+        assert_eq!(semitones, (1..13).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn interval_between_notes() {
+        let between = |a: &str, b: &str| Interval::between(note(a), note(b));
+
+        assert_eq!(between("C4", "D#4"), Some(Interval::MinorThird));
+        assert_eq!(between("C4", "Eb4"), Some(Interval::MinorThird));
+        assert_eq!(between("E4", "C4"), Some(Interval::MajorThird));
+        assert_eq!(between("B3", "Cb5"), Some(Interval::PerfectOctave));
+        assert_eq!(between("C4", "B#3"), None);
+        assert_eq!(between("C4", "D5"), None);
+
+        for interval in INTERVALS.iter() {
+            let other = interval.above(note("F4")).unwrap();
+            assert_eq!(Interval::between(note("F4"), other), Some(*interval));
+        }
+    }
+
+    #[test]
+    fn question_notes() {
+        let q = Question::new(note("C4"), Interval::MajorThird, Direction::Ascending).unwrap();
+        assert_eq!(q.notes(), [note("C4"), note("E4")]);
+
+        let q = Question::new(note("E4"), Interval::MinorThird, Direction::Descending).unwrap();
+        assert_eq!(q.notes(), [note("E4"), note("C#4")]);
+
+        let q = Question::new(note("F4"), Interval::Tritone, Direction::Harmonic).unwrap();
+        assert_eq!(q.notes(), [note("F4"), note("B4")]);
+
+        let q = Question::new(note("A4"), Interval::PerfectOctave, Direction::Descending).unwrap();
+        assert_eq!(q.notes(), [note("A4"), note("A3")]);
+
+        assert!(
+            Question::new(note("Db4"), Interval::MajorSeventh, Direction::Descending).is_none()
+        );
+    }
+
+    #[test]
+    fn question_display() {
+        let q = Question::new(note("C4"), Interval::MajorThird, Direction::Ascending).unwrap();
+        assert_eq!(q.to_string(), "C4+M3");
+
+        let q = Question::new(note("Bb4"), Interval::MinorSixth, Direction::Descending).unwrap();
+        assert_eq!(q.to_string(), "Bb4-m6");
+
+        let q = Question::new(note("F#3"), Interval::Tritone, Direction::Harmonic).unwrap();
+        assert_eq!(q.to_string(), "F#3=TT");
+    }
+
+    #[test]
+    fn parse_question_from_str() {
+        let q = "Bb4-m6".parse::<Question>().unwrap();
+        assert_eq!(q.root, note("Bb4"));
+        assert_eq!(q.interval, Interval::MinorSixth);
+        assert_eq!(q.direction, Direction::Descending);
+        assert_eq!(q.notes(), [note("Bb4"), note("D4")]);
+
+        assert!("C4".parse::<Question>().is_err());
+        assert!("C4+M9".parse::<Question>().is_err());
+    }
+}
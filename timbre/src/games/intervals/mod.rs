@@ -0,0 +1,228 @@
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+
+pub use self::state::State;
+use self::interval::{Direction, Interval, Question, INTERVALS};
+use self::models::{GameState, NewGame, NewGameState};
+use games::octaves::note::Note;
+use games::{Game, Score, Session, SharedSession};
+use player::{AudioBackend, EarsBackend};
+use schema::{interval_game_states, interval_games};
+
+pub mod interval;
+pub mod models;
+mod state;
+
+lazy_static! {
+    static ref EXERCISES: Vec<Exercise> = {
+        let mut v = Vec::new();
+
+        let mut intervals = Vec::new();
+        intervals.push(Interval::MajorThird);
+        intervals.push(Interval::PerfectFifth);
+        intervals.push(Interval::PerfectOctave);
+        v.push(Exercise {
+            num: 1,
+            intervals,
+            directions: vec![Direction::Ascending],
+        });
+
+        let mut intervals = Vec::new();
+        intervals.push(Interval::MinorThird);
+        intervals.push(Interval::MajorThird);
+        intervals.push(Interval::PerfectFourth);
+        intervals.push(Interval::PerfectFifth);
+        intervals.push(Interval::PerfectOctave);
+        v.push(Exercise {
+            num: 2,
+            intervals,
+            directions: vec![Direction::Ascending],
+        });
+
+        let intervals = INTERVALS.clone();
+        v.push(Exercise {
+            num: 3,
+            intervals,
+            directions: vec![Direction::Ascending],
+        });
+
+        let intervals = INTERVALS.clone();
+        v.push(Exercise {
+            num: 4,
+            intervals,
+            directions: vec![Direction::Descending],
+        });
+
+        let intervals = INTERVALS.clone();
+        v.push(Exercise {
+            num: 5,
+            intervals,
+            directions: vec![Direction::Harmonic],
+        });
+
+        v
+    };
+}
+
+#[derive(Clone, Debug)]
+pub struct Exercise {
+    pub num: u8,
+    intervals: Vec<Interval>,
+    directions: Vec<Direction>,
+}
+
+pub type Controller<B = EarsBackend> = Session<State, B>;
+
+pub type SharedController<B = EarsBackend> = SharedSession<State, B>;
+
+impl Game for State {
+    type Settings = ();
+    type Exercise = Exercise;
+    type Question = Question;
+    type Answer = Interval;
+
+    const NAME: &'static str = "intervals";
+
+    fn create(_: (), seed: u64) -> State {
+        let exercise = EXERCISES.first().cloned().unwrap();
+        State::new(exercise, seed)
+    }
+
+    fn question(&self) -> Option<Question> {
+        self.question
+    }
+
+    fn next_question(&mut self) -> Option<Question> {
+        State::next_question(self)
+    }
+
+    fn check_answer(&self, question: Question, answer: &Interval) -> bool {
+        question.interval == *answer
+    }
+
+    fn next_exercise(&mut self) -> Option<&'static Exercise> {
+        State::next_exercise(self)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.questions.is_empty()
+    }
+
+    fn notes(&self) -> Vec<Note> {
+        self.questions.iter().flat_map(|q| q.notes().to_vec()).collect()
+    }
+
+    fn score(&self) -> &Score {
+        &self.score
+    }
+
+    fn score_mut(&mut self) -> &mut Score {
+        &mut self.score
+    }
+
+    fn play(&self, question: Question, player: &AudioBackend) {
+        match question.direction {
+            Direction::Harmonic => player.play_chord(&question.notes()),
+            _ => {
+                for note in question.notes().iter() {
+                    player.play_note(*note);
+                }
+            }
+        }
+    }
+
+    fn insert(&self, conn: &SqliteConnection) -> QueryResult<()> {
+        let new_game = NewGame {
+            seed: self.seed as i64,
+        };
+
+        diesel::insert_into(interval_games::table)
+            .values(&new_game)
+            .execute(conn)
+            .map(|_| ())
+    }
+
+    fn load(conn: &SqliteConnection) -> QueryResult<Option<State>> {
+        let game = match current_game(conn)? {
+            Some(game) => game,
+            None => return Ok(None),
+        };
+
+        let game_state = GameState::belonging_to(&game)
+            .first::<GameState>(conn)
+            .optional()?;
+
+        let game_state = match game_state {
+            Some(game_state) => game_state,
+            None => return Ok(None),
+        };
+
+        match State::load(&game_state, game.seed as u64) {
+            Some(state) => Ok(Some(state)),
+            None => {
+                // The exercise is gone or the state is broken,
+                // a new game starts instead
+                Self::finish(conn)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn save(&self, conn: &SqliteConnection) -> QueryResult<()> {
+        let game = match current_game(conn)? {
+            Some(game) => game,
+            None => return Ok(()),
+        };
+
+        let game_state = GameState::belonging_to(&game)
+            .first::<GameState>(conn)
+            .optional()?;
+        match game_state {
+            Some(current_state) => {
+                let changeset = self.changeset();
+
+                diesel::update(&current_state)
+                    .set(&changeset)
+                    .execute(conn)?;
+            }
+            None => {
+                let changeset = self.changeset();
+                let new_state = NewGameState {
+                    exercise: changeset.exercise,
+                    question: changeset.question,
+                    questions: changeset.questions,
+                    right_count: changeset.right_count,
+                    total_count: changeset.total_count,
+                    game_id: game.id,
+                    draws: changeset.draws,
+                };
+
+                diesel::insert_into(interval_game_states::table)
+                    .values(&new_state)
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(conn: &SqliteConnection) -> QueryResult<()> {
+        if let Some(game) = current_game(conn)? {
+            diesel::update(interval_games::table)
+                .set(interval_games::finished_at.eq(Utc::now().naive_utc()))
+                .filter(interval_games::id.eq(game.id))
+                .execute(conn)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn current_game(conn: &SqliteConnection) -> QueryResult<Option<models::Game>> {
+    interval_games::table
+        .filter(interval_games::finished_at.is_null())
+        .order(interval_games::created_at.desc())
+        .first::<models::Game>(conn)
+        .optional()
+}
@@ -0,0 +1,239 @@
+use rand::Rng;
+use games::intervals::{Exercise, EXERCISES};
+
+use super::interval::{Direction, Question};
+use games::intervals::models::{GameState, GameStateChangeset};
+use games::replay::ReplayRng;
+use games::Score;
+use games::octaves::note::{Note, Octave, Pitch};
+
+// Each interval is asked this many times in every direction
+const REPEATS: usize = 2;
+
+const ROOTS: [Pitch; 12] = [
+    Pitch::C,
+    Pitch::Dflat,
+    Pitch::D,
+    Pitch::Eflat,
+    Pitch::E,
+    Pitch::F,
+    Pitch::Fsharp,
+    Pitch::G,
+    Pitch::Aflat,
+    Pitch::A,
+    Pitch::Bflat,
+    Pitch::B,
+];
+
+#[derive(Debug)]
+pub struct State {
+    // Current exercise
+    pub exercise: Exercise,
+    // Current question
+    pub question: Option<Question>,
+    // Questions to ask
+    pub questions: Vec<Question>,
+    // Answers count
+    pub score: Score,
+    // The same seed gives the same questions
+    pub seed: u64,
+    rng: ReplayRng,
+}
+
+impl State {
+    pub fn new(exercise: Exercise, seed: u64) -> State {
+        let mut state = State {
+            exercise,
+            question: None,
+            questions: vec![],
+            score: Score::default(),
+            seed,
+            rng: ReplayRng::new(seed, 0),
+        };
+
+        state.generate_questions();
+        state
+    }
+
+    // A loaded game goes on with the same questions, the drawn numbers
+    // are replayed from the seed.
+    // Returns `None` if the exercise is gone or the state can't be read.
+    pub fn load(game_state: &GameState, seed: u64) -> Option<State> {
+        let exercise = EXERCISES
+            .iter()
+            .find(|&ex| ex.num == game_state.exercise as u8)
+            .cloned()?;
+        // Empty if the game was saved before the first question
+        let question = match game_state.question.as_str() {
+            "" => None,
+            question => Some(question.parse::<Question>().ok()?),
+        };
+        let questions = game_state
+            .questions
+            .split(",")
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<Question>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(State {
+            exercise,
+            question,
+            questions,
+            score: Score {
+                right_count: game_state.right_count as u32,
+                total_count: game_state.total_count as u32,
+            },
+            seed,
+            rng: ReplayRng::new(seed, game_state.draws as u64),
+        })
+    }
+
+    pub fn changeset(&self) -> GameStateChangeset {
+        GameStateChangeset {
+            exercise: self.exercise.num as i32,
+            question: self.question.map_or("".to_owned(), |q| q.to_string()),
+            questions: self.questions
+                .iter()
+                .map(|q| q.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            right_count: self.score.right_count as i32,
+            total_count: self.score.total_count as i32,
+            draws: self.rng.draws() as i64,
+        }
+    }
+
+    fn generate_questions(&mut self) {
+        let mut roots = ROOTS.to_vec();
+
+        for direction in self.exercise.directions.iter() {
+            // Descending intervals start an octave higher to stay in range
+            let octave = match *direction {
+                Direction::Descending => Octave::Second,
+                _ => Octave::First,
+            };
+
+            for interval in self.exercise.intervals.iter() {
+                for _ in 0..REPEATS {
+                    self.rng.shuffle(&mut roots);
+
+                    let question = roots
+                        .iter()
+                        .filter_map(|&pitch| {
+                            let root = Note { octave, pitch };
+                            Question::new(root, *interval, *direction)
+                        })
+                        .next();
+
+                    if let Some(q) = question {
+                        self.questions.push(q);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn next_question(&mut self) -> Option<Question> {
+        self.drop_question();
+        self.question = self.rng.choose(&self.questions).map(|q| *q);
+        self.question
+    }
+
+    fn drop_question(&mut self) {
+        let question = self.question;
+        if let Some(index) = self.questions.iter().position(|&q| Some(q) == question) {
+            self.questions.remove(index);
+        }
+    }
+
+    pub fn next_exercise(&mut self) -> Option<&'static Exercise> {
+        let num = self.exercise.num + 1;
+        EXERCISES.iter().find(|&ex| ex.num == num).and_then(|ex| {
+            self.exercise = ex.clone();
+            self.generate_questions();
+
+            Some(ex)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::interval::Interval;
+
+    #[test]
+    fn generate_questions_first_ex() {
+        let exercise = EXERCISES.iter().nth(0).cloned().unwrap();
+        let state = State::new(exercise.clone(), 1);
+
+        assert_eq!(
+            state.questions.len(),
+            exercise.intervals.len() * exercise.directions.len() * REPEATS
+        );
+
+        for interval in exercise.intervals.iter() {
+            let count = state
+                .questions
+                .iter()
+                .filter(|q| q.interval == *interval)
+                .count();
+            assert_eq!(count, REPEATS);
+        }
+
+        for q in state.questions.iter() {
+            assert_eq!(q.direction, Direction::Ascending);
+            assert_eq!(q.root.octave, Octave::First);
+        }
+    }
+
+    #[test]
+    fn generate_questions_descending_ex() {
+        let exercise = EXERCISES
+            .iter()
+            .find(|ex| ex.directions == vec![Direction::Descending])
+            .cloned()
+            .unwrap();
+        let state = State::new(exercise, 1);
+
+        assert_eq!(state.questions.len(), 12 * REPEATS);
+
+        let sevenths = state
+            .questions
+            .iter()
+            .filter(|q| q.interval == Interval::MajorSeventh)
+            .count();
+        assert_eq!(sevenths, REPEATS);
+    }
+
+    #[test]
+    fn next_question_first_ex() {
+        let exercise = EXERCISES.iter().nth(0).cloned().unwrap();
+        let mut state = State::new(exercise, 1);
+        let total = state.questions.len();
+
+        assert!(state.question.is_none());
+
+        for _ in 0..total {
+            assert!(state.next_question().is_some());
+        }
+
+        assert!(state.next_question().is_none());
+        assert!(state.questions.is_empty());
+    }
+
+    #[test]
+    fn next_exercise() {
+        let exercise = EXERCISES.iter().nth(0).cloned().unwrap();
+        let mut state = State::new(exercise, 1);
+
+        while state.next_question().is_some() {}
+
+        let exercise = state.next_exercise().unwrap();
+        assert_eq!(exercise.num, 2);
+        assert_eq!(
+            state.questions.len(),
+            exercise.intervals.len() * exercise.directions.len() * REPEATS
+        );
+    }
+}
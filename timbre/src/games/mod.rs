@@ -1 +1,164 @@
+use diesel::QueryResult;
+use diesel::sqlite::SqliteConnection;
+
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use config::Config;
+use games::octaves::note::Note;
+use player::{AudioBackend, RenderingBackend};
+
+pub use self::session::{Session, SharedSession};
+
+pub mod chords;
+pub mod intervals;
 pub mod octaves;
+mod replay;
+mod session;
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Score {
+    // Right answered count
+    pub right_count: u32,
+    // Total played count
+    pub total_count: u32,
+}
+
+// Answer to a question, logged for the analytics
+pub struct AnswerRecord<'a, G: Game> {
+    pub question: G::Question,
+    pub answer: &'a G::Answer,
+    pub right: bool,
+    // Since the question was played the first time
+    pub response_time: Duration,
+    // Times the question was played again before the answer
+    pub repeats: u32,
+}
+
+// Rules of an ear training game, a `Session` takes care of the rest
+pub trait Game: Sized + 'static {
+    // Parameters chosen by the user to start a game
+    type Settings;
+    type Exercise: fmt::Debug + 'static;
+    type Question: Copy + fmt::Display;
+    type Answer;
+
+    // Key of the game in the config, e.g. to choose the instrument
+    const NAME: &'static str;
+
+    // Game starting from the first exercise. The games that can be
+    // replayed choose the questions by the seed.
+    fn create(settings: Self::Settings, seed: u64) -> Self;
+
+    fn question(&self) -> Option<Self::Question>;
+
+    fn next_question(&mut self) -> Option<Self::Question>;
+
+    fn check_answer(&self, question: Self::Question, answer: &Self::Answer) -> bool;
+
+    // Unlock the next exercise, if any
+    fn next_exercise(&mut self) -> Option<&'static Self::Exercise>;
+
+    // Enough right answers to unlock the next exercise,
+    // all of them by default
+    fn is_passed(&self) -> bool {
+        self.score().right_count == self.score().total_count
+    }
+
+    fn is_finished(&self) -> bool;
+
+    // Notes left to play in the current exercise
+    fn notes(&self) -> Vec<Note>;
+
+    fn score(&self) -> &Score;
+
+    fn score_mut(&mut self) -> &mut Score;
+
+    fn play(&self, question: Self::Question, player: &AudioBackend);
+
+    // Prepare the ear before the questions, e.g. play a cadence
+    fn play_intro(&self, _player: &AudioBackend) {}
+
+    // Read what the game remembers from the past games,
+    // called before the first question of a new or a loaded game
+    fn prepare(&mut self, _conn: &SqliteConnection, _config: &Config) -> QueryResult<()> {
+        Ok(())
+    }
+
+    // Learn from the checked answer, e.g. ask a hard question again
+    fn answered(&mut self, _record: &AnswerRecord<Self>) {}
+
+    // Persist a new game
+    fn insert(&self, conn: &SqliteConnection) -> QueryResult<()>;
+
+    // Restore the last unfinished game
+    fn load(conn: &SqliteConnection) -> QueryResult<Option<Self>>;
+
+    fn save(&self, conn: &SqliteConnection) -> QueryResult<()>;
+
+    // Mark the last unfinished game as finished
+    fn finish(conn: &SqliteConnection) -> QueryResult<()>;
+
+    // Keep the answer of the current game, the games without
+    // the analytics ignore it
+    fn log_answer(
+        &self,
+        _conn: &SqliteConnection,
+        _record: &AnswerRecord<Self>,
+    ) -> QueryResult<()> {
+        Ok(())
+    }
+}
+
+// Render the intro and every question left in the current exercise
+// into a WAV track with the sounds of the config, each sound
+// is followed by `gap` seconds of silence
+pub fn render_exercise<G, W>(mut game: G, config: &Config, gap: f32, out: &mut W) -> io::Result<()>
+where
+    G: Game,
+    W: Write,
+{
+    let mut renderer = RenderingBackend::with_config(gap, config, G::NAME);
+
+    game.play_intro(&renderer);
+    while let Some(question) = game.next_question() {
+        renderer.next_instrument();
+        game.play(question, &renderer);
+    }
+
+    renderer.write(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Sounds;
+    use games::octaves::exercise::default_exercises;
+    use games::octaves::note::{Pitch, Tonality};
+    use games::octaves::State;
+    use synth;
+    use wav::SAMPLE_RATE;
+
+    #[test]
+    fn render_octaves_exercise() {
+        let tonality = Tonality::major(Pitch::D);
+        let state = State::new(tonality, default_exercises()[0].clone(), 0);
+        let notes = state.notes.clone();
+
+        let mut out = Vec::new();
+        let config = Config {
+            sounds: Sounds::Synth,
+            ..Config::default()
+        };
+        render_exercise(state, &config, 1.0, &mut out).unwrap();
+
+        let cadence = synth::render_cadence(tonality).len();
+        let note = synth::render_note(notes[0]).len();
+        let gap = SAMPLE_RATE as usize;
+        let samples = cadence + gap + notes.len() * (note + gap);
+
+        assert_eq!(&out[0..4], b"RIFF");
+        assert_eq!(out.len(), 44 + samples * 2);
+    }
+}
@@ -0,0 +1,445 @@
+use serde::de;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use toml;
+
+use super::note::{AlteredDegree, Octave};
+use error::Error;
+use xdg_dirs;
+
+lazy_static! {
+    // The curriculum of the teacher if there is a valid one,
+    // the built-in one otherwise
+    pub static ref EXERCISES: Vec<Exercise> = match *CURRICULUM {
+        Ok(ref exercises) if !exercises.is_empty() => exercises.clone(),
+        _ => default_exercises(),
+    };
+
+    pub static ref DIR: PathBuf = xdg_dirs::CONFIG.join("exercises");
+
+    static ref CURRICULUM: Result<Vec<Exercise>, Error> = load(&DIR);
+}
+
+// Exercise of the octaves game, e.g. in `exercises/major.toml`:
+//
+// [[exercise]]
+// num = 1
+// octaves = [4]
+// degrees = [1, 3, 5]
+// questions = 10
+// pass = 0.8
+// unlocks = 3
+// chromatic = true
+// stress = ["b3", "#4", "b7"]
+//
+// A misspelled key is an error instead of a silent default.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Exercise {
+    pub num: u8,
+    #[serde(deserialize_with = "deserialize_octaves")]
+    pub octaves: Vec<Octave>,
+    // Scale degrees from 1, the first one adds the tonic
+    // of the next octave too
+    #[serde(default = "all_degrees")]
+    pub degrees: Vec<u8>,
+    // Every note is asked once by default
+    #[serde(default)]
+    pub questions: Option<usize>,
+    // Share of the right answers to unlock the next exercise
+    #[serde(default = "full_pass")]
+    pub pass: f32,
+    // The next one by number by default
+    #[serde(default)]
+    pub unlocks: Option<u8>,
+    // Add the notes out of the scale
+    #[serde(default)]
+    pub chromatic: bool,
+    // Chromatic degrees asked twice as often
+    #[serde(default, deserialize_with = "deserialize_degrees")]
+    pub stress: Vec<AlteredDegree>,
+}
+
+impl Exercise {
+    fn new(num: u8, octaves: Vec<Octave>) -> Exercise {
+        Exercise {
+            num,
+            octaves,
+            degrees: all_degrees(),
+            questions: None,
+            pass: full_pass(),
+            unlocks: None,
+            chromatic: false,
+            stress: Vec::new(),
+        }
+    }
+
+    // Exercise unlocked by this one
+    pub fn next(&self) -> Option<&'static Exercise> {
+        unlocked(&EXERCISES, self)
+    }
+
+    // Enough right answers to unlock the next exercise
+    pub fn is_passed(&self, right_count: u32, total_count: u32) -> bool {
+        right_count as f32 >= self.pass * total_count as f32
+    }
+}
+
+// Why the exercises of the teacher are replaced by the built-in ones
+pub fn load_error() -> Option<&'static Error> {
+    CURRICULUM.as_ref().err()
+}
+
+pub fn find(num: u8) -> Option<&'static Exercise> {
+    EXERCISES.iter().find(|ex| ex.num == num)
+}
+
+// Exercise of the curriculum unlocked by the given one,
+// the next one by number by default
+fn unlocked<'a>(exercises: &'a [Exercise], exercise: &Exercise) -> Option<&'a Exercise> {
+    match exercise.unlocks {
+        Some(num) => exercises.iter().find(|ex| ex.num == num),
+        None => exercises
+            .iter()
+            .filter(|ex| ex.num > exercise.num)
+            .min_by_key(|ex| ex.num),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Curriculum {
+    #[serde(default)]
+    exercise: Vec<Exercise>,
+}
+
+// Read the exercises from the TOML files in the directory,
+// ordered by number. Empty if there are no files.
+pub fn load(dir: &Path) -> Result<Vec<Exercise>, Error> {
+    let mut paths = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |ext| ext == "toml"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    paths.sort();
+
+    let mut exercises = Vec::new();
+    for path in paths.iter() {
+        let mut serialized = String::new();
+        File::open(path)?.read_to_string(&mut serialized)?;
+
+        let curriculum = toml::from_str::<Curriculum>(&serialized).map_err(|e| {
+            Error::InvalidExercise(format!("{}: {}", path.display(), e))
+        })?;
+        exercises.extend(curriculum.exercise);
+    }
+
+    if !exercises.is_empty() {
+        validate(&mut exercises)?;
+    }
+    Ok(exercises)
+}
+
+pub fn parse(serialized: &str) -> Result<Vec<Exercise>, Error> {
+    let mut exercises = toml::from_str::<Curriculum>(serialized)?.exercise;
+    validate(&mut exercises)?;
+    Ok(exercises)
+}
+
+// Check that every exercise can be played and unlocked,
+// then order them by number
+fn validate(exercises: &mut Vec<Exercise>) -> Result<(), Error> {
+    let invalid = |num: u8, msg: &str| Err(Error::InvalidExercise(format!("#{}: {}", num, msg)));
+
+    if exercises.is_empty() {
+        return Err(Error::InvalidExercise("no exercises".to_owned()));
+    }
+
+    exercises.sort_by_key(|ex| ex.num);
+    if let Some(pair) = exercises.windows(2).find(|pair| pair[0].num == pair[1].num) {
+        return invalid(pair[0].num, "the number is used twice");
+    }
+
+    let nums = exercises.iter().map(|ex| ex.num).collect::<BTreeSet<_>>();
+    for ex in exercises.iter_mut() {
+        if ex.octaves.is_empty() {
+            return invalid(ex.num, "no octaves");
+        }
+        if ex.degrees.is_empty() || ex.degrees.iter().any(|&d| d < 1 || d > 7) {
+            return invalid(ex.num, "the degrees must be from 1 to 7");
+        }
+        if ex.questions == Some(0) {
+            return invalid(ex.num, "no questions");
+        }
+        // Also false for NaN, which would never unlock the next exercise
+        if !(ex.pass >= 0.0 && ex.pass <= 1.0) {
+            return invalid(ex.num, "the pass must be from 0 to 1");
+        }
+        match ex.unlocks {
+            Some(num) if num == ex.num => return invalid(ex.num, "unlocks itself"),
+            Some(num) if !nums.contains(&num) => {
+                return invalid(ex.num, &format!("unlocks missing #{}", num))
+            }
+            _ => {}
+        }
+        if !ex.stress.is_empty() && !ex.chromatic {
+            return invalid(ex.num, "stresses degrees without the chromatic notes");
+        }
+
+        ex.octaves.sort();
+        ex.octaves.dedup();
+        ex.degrees.sort();
+        ex.degrees.dedup();
+    }
+
+    // Otherwise the game would never be over
+    for ex in exercises.iter() {
+        let mut visited = BTreeSet::new();
+        let mut current = Some(ex);
+        while let Some(ex) = current {
+            if !visited.insert(ex.num) {
+                return invalid(ex.num, "the unlocks go round in a circle");
+            }
+            current = unlocked(exercises, ex);
+        }
+    }
+
+    Ok(())
+}
+
+fn deserialize_octaves<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Octave>, D::Error> {
+    Vec::<i8>::deserialize(deserializer)?
+        .into_iter()
+        .map(|number| {
+            Octave::from_number(number)
+                .ok_or_else(|| de::Error::custom(format!("no octave {}", number)))
+        })
+        .collect()
+}
+
+fn deserialize_degrees<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<AlteredDegree>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|s| s.parse().map_err(|_| de::Error::custom(format!("no degree {}", s))))
+        .collect()
+}
+
+fn all_degrees() -> Vec<u8> {
+    (1..8).collect()
+}
+
+fn full_pass() -> f32 {
+    1.0
+}
+
+pub fn default_exercises() -> Vec<Exercise> {
+    let mut v = Vec::new();
+
+    let mut octaves = Vec::new();
+    octaves.push(Octave::First);
+    v.push(Exercise::new(1, octaves));
+
+    let mut octaves = Vec::new();
+    octaves.push(Octave::First);
+    octaves.push(Octave::Second);
+    v.push(Exercise::new(2, octaves));
+
+    let mut octaves = Vec::new();
+    octaves.push(Octave::Small);
+    octaves.push(Octave::First);
+    v.push(Exercise::new(3, octaves));
+
+    let mut octaves = Vec::new();
+    octaves.push(Octave::First);
+    octaves.push(Octave::Second);
+    octaves.push(Octave::Third);
+    v.push(Exercise::new(4, octaves));
+
+    let mut octaves = Vec::new();
+    octaves.push(Octave::Great);
+    octaves.push(Octave::Small);
+    octaves.push(Octave::First);
+    v.push(Exercise::new(5, octaves));
+
+    let mut octaves = Vec::new();
+    octaves.push(Octave::Small);
+    octaves.push(Octave::First);
+    octaves.push(Octave::Second);
+    octaves.push(Octave::Third);
+    v.push(Exercise::new(6, octaves));
+
+    let mut octaves = Vec::new();
+    octaves.push(Octave::Great);
+    octaves.push(Octave::Small);
+    octaves.push(Octave::First);
+    octaves.push(Octave::Second);
+    octaves.push(Octave::Third);
+    v.push(Exercise::new(7, octaves));
+
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    #[test]
+    fn parse_exercises() {
+        let exercises = parse(
+            r##"
+            [[exercise]]
+            num = 2
+            octaves = [5, 4]
+            degrees = [5, 1, 3]
+            questions = 12
+            pass = 0.75
+
+            [[exercise]]
+            num = 1
+            octaves = [4]
+            unlocks = 2
+
+            [[exercise]]
+            num = 3
+            octaves = [4]
+            chromatic = true
+            stress = ["b3", "#4"]
+            "##,
+        ).unwrap();
+
+        assert_eq!(exercises.len(), 3);
+        assert_eq!(exercises[0], Exercise {
+            unlocks: Some(2),
+            ..Exercise::new(1, vec![Octave::First])
+        });
+        assert_eq!(exercises[1].octaves, vec![Octave::First, Octave::Second]);
+        assert_eq!(exercises[1].degrees, vec![1, 3, 5]);
+        assert_eq!(exercises[1].questions, Some(12));
+        assert!(exercises[1].is_passed(9, 12));
+        assert!(!exercises[1].is_passed(8, 12));
+        assert!(exercises[2].chromatic);
+        assert_eq!(
+            exercises[2].stress,
+            vec![AlteredDegree::new(3, -1), AlteredDegree::new(4, 1)]
+        );
+    }
+
+    #[test]
+    fn reject_invalid_exercises() {
+        let invalid = [
+            "",
+            "[[exercise]]\nnum = 1\noctaves = [9]",
+            "[[exercise]]\nnum = 1\noctaves = []",
+            "[[exercise]]\nnum = 1\noctaves = [4]\ndegrees = [0, 1]",
+            "[[exercise]]\nnum = 1\noctaves = [4]\ndegrees = [8]",
+            "[[exercise]]\nnum = 1\noctaves = [4]\nquestions = 0",
+            "[[exercise]]\nnum = 1\noctaves = [4]\npass = 1.5",
+            "[[exercise]]\nnum = 1\noctaves = [4]\nunlocks = 1",
+            "[[exercise]]\nnum = 1\noctaves = [4]\nunlocks = 2",
+            "[[exercise]]\nnum = 1\noctaves = [4]\nstress = [\"b3\"]",
+            "[[exercise]]\nnum = 1\noctaves = [4]\nchromatic = true\nstress = [\"b9\"]",
+            "[[exercise]]\nnum = 1\noctaves = [4]\n[[exercise]]\nnum = 1\noctaves = [5]",
+            "[[exercise]]\nnum = 1\noctaves = [4]\nquestion = 10",
+            "[[exercises]]\nnum = 1\noctaves = [4]",
+        ];
+
+        for s in invalid.iter() {
+            assert!(parse(s).is_err(), "{:?}", s);
+        }
+
+        for &pass in [f32::NAN, -0.5, f32::INFINITY].iter() {
+            let mut exercises = vec![Exercise {
+                pass,
+                ..Exercise::new(1, vec![Octave::First])
+            }];
+            match validate(&mut exercises) {
+                Err(Error::InvalidExercise(msg)) => assert!(msg.contains("pass"), "{}", msg),
+                res => panic!("Expected an invalid pass {}, got {:?}", pass, res),
+            }
+        }
+    }
+
+    #[test]
+    fn reject_unlock_cycles() {
+        let cycles = [
+            "[[exercise]]\nnum = 1\noctaves = [4]\nunlocks = 2\n\
+             [[exercise]]\nnum = 2\noctaves = [4]\nunlocks = 1",
+            // The first one unlocks the second one by number
+            "[[exercise]]\nnum = 1\noctaves = [4]\n\
+             [[exercise]]\nnum = 2\noctaves = [4]\nunlocks = 1",
+            "[[exercise]]\nnum = 1\noctaves = [4]\nunlocks = 3\n\
+             [[exercise]]\nnum = 2\noctaves = [4]\n\
+             [[exercise]]\nnum = 3\noctaves = [4]\nunlocks = 2",
+        ];
+
+        for s in cycles.iter() {
+            match parse(s) {
+                Err(Error::InvalidExercise(msg)) => assert!(msg.contains("circle"), "{}", msg),
+                res => panic!("Expected a cycle in {:?}, got {:?}", s, res),
+            }
+        }
+
+        // The second one is skipped, the third one is the last
+        let chain = "[[exercise]]\nnum = 1\noctaves = [4]\nunlocks = 3\n\
+                     [[exercise]]\nnum = 2\noctaves = [4]\n\
+                     [[exercise]]\nnum = 3\noctaves = [4]";
+        assert!(parse(chain).is_ok());
+    }
+
+    #[test]
+    fn load_exercises_from_files() {
+        let tmp = TempDir::new("exercises").unwrap();
+        assert_eq!(load(tmp.path()).unwrap(), vec![]);
+
+        let files = [
+            ("b.toml", "[[exercise]]\nnum = 1\noctaves = [4]"),
+            ("a.toml", "[[exercise]]\nnum = 3\noctaves = [3, 4]"),
+            ("notes.txt", "Not an exercise"),
+        ];
+        for &(name, content) in files.iter() {
+            let mut file = File::create(tmp.path().join(name)).unwrap();
+            file.write_all(content.as_bytes()).unwrap();
+        }
+
+        let nums = load(tmp.path())
+            .unwrap()
+            .iter()
+            .map(|ex| ex.num)
+            .collect::<Vec<_>>();
+        assert_eq!(nums, vec![1, 3]);
+
+        let mut file = File::create(tmp.path().join("c.toml")).unwrap();
+        file.write_all(b"[[exercise]]\nnum = 3\noctaves = [5]").unwrap();
+        match load(tmp.path()) {
+            Err(Error::InvalidExercise(msg)) => assert!(msg.contains("#3"), "{}", msg),
+            res => panic!("Expected an invalid exercise, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn unlock_order() {
+        let exercises = default_exercises();
+        assert_eq!(unlocked(&exercises, &exercises[0]).unwrap().num, 2);
+        assert!(unlocked(&exercises, &exercises[6]).is_none());
+
+        let ex = Exercise {
+            unlocks: Some(5),
+            ..exercises[1].clone()
+        };
+        assert_eq!(unlocked(&exercises, &ex).unwrap().num, 5);
+    }
+}
@@ -1,392 +1,222 @@
 use chrono::Utc;
+use config::Config;
 use diesel;
 use diesel::prelude::*;
-use ears::{AudioController, Sound};
-
-use std::thread;
-use std::path::PathBuf;
-use std::sync::mpsc;
-use std::cell::RefCell;
-use std::rc::Rc;
-
-pub use self::config::Config;
-use self::state::State;
-use self::note::{Note, Octave, Pitch, Tonality};
-use games::octaves::models::{Game, GameState, NewGame, NewGameState};
-use establish_connection;
-use schema::{octave_games, octave_game_states};
-use xdg_dirs;
-
-mod config;
+
+pub use self::exercise::{Exercise, EXERCISES};
+pub use self::state::State;
+use self::note::{Note, Pitch, Tonality};
+use self::models::{GameState, NewAnswer, NewGame, NewGameState};
+use self::scheduler::Scheduler;
+use games::{AnswerRecord, Game, Score, Session, SharedSession};
+use player::{AudioBackend, EarsBackend};
+use schema::{octave_answers, octave_games, octave_game_states};
+
+pub mod exercise;
 pub mod models;
 pub mod note;
+pub mod scheduler;
 mod state;
 
-lazy_static! {
-    static ref NOTES_PATH: PathBuf = xdg_dirs::SAMPLES.join("notes");
-    static ref TONES_PATH: PathBuf = xdg_dirs::SAMPLES.join("tonal-centers");
-
-    static ref EXERCISES: Vec<Exercise> = {
-        let mut v = Vec::new();
-
-        let mut octaves = Vec::new();
-        octaves.push(Octave::First);
-        v.push(Exercise { num: 1, octaves: octaves });
-
-        let mut octaves = Vec::new();
-        octaves.push(Octave::First);
-        octaves.push(Octave::Second);
-        v.push(Exercise { num: 2, octaves: octaves });
-
-        let mut octaves = Vec::new();
-        octaves.push(Octave::Small);
-        octaves.push(Octave::First);
-        v.push(Exercise { num: 3, octaves: octaves });
-
-        let mut octaves = Vec::new();
-        octaves.push(Octave::First);
-        octaves.push(Octave::Second);
-        octaves.push(Octave::Third);
-        v.push(Exercise { num: 4, octaves: octaves });
-
-        let mut octaves = Vec::new();
-        octaves.push(Octave::Great);
-        octaves.push(Octave::Small);
-        octaves.push(Octave::First);
-        v.push(Exercise { num: 5, octaves: octaves });
-
-        let mut octaves = Vec::new();
-        octaves.push(Octave::Small);
-        octaves.push(Octave::First);
-        octaves.push(Octave::Second);
-        octaves.push(Octave::Third);
-        v.push(Exercise { num: 6, octaves: octaves });
-
-        let mut octaves = Vec::new();
-        octaves.push(Octave::Great);
-        octaves.push(Octave::Small);
-        octaves.push(Octave::First);
-        octaves.push(Octave::Second);
-        octaves.push(Octave::Third);
-        v.push(Exercise { num: 7, octaves: octaves });
-
-        v
-    };
-}
+pub type Controller<B = EarsBackend> = Session<State, B>;
 
-#[derive(Clone, Debug)]
-pub struct Exercise {
-    pub num: u8,
-    octaves: Vec<Octave>,
-}
+pub type SharedController<B = EarsBackend> = SharedSession<State, B>;
 
-type Sample = String;
+impl Game for State {
+    type Settings = Tonality;
+    type Exercise = Exercise;
+    type Question = Note;
 
-pub struct Controller {
-    config: Config,
-    gramophone: mpsc::Sender<Sample>,
-    state: Option<State>,
-    // tonality: Option<Tonality>,
-    count_observers: Vec<Box<Fn(&Controller) -> ()>>,
-    pub next_exercise_observer: Option<Box<Fn(&'static Exercise) -> ()>>,
-    pub game_over_observer: Option<Box<Fn() -> ()>>,
-}
+    // The sounding pitch is checked, so any spelling of it is right
+    type Answer = Pitch;
 
-pub type SharedController = Rc<RefCell<Controller>>;
+    const NAME: &'static str = "octaves";
 
-impl Controller {
-    pub fn new(config: Config) -> Controller {
-        let (tx, rx) = mpsc::channel::<Sample>();
-
-        thread::spawn(move || {
-            for path in rx.iter() {
-                let sample = &path;
-                let mut snd = Sound::new(sample).unwrap();
-                snd.play();
-                while snd.is_playing() {}
-            }
-        });
-
-        Controller {
-            config,
-            gramophone: tx,
-            state: None,
-            // TODO: is it required?
-            // tonality: None,
-            count_observers: Vec::new(),
-            next_exercise_observer: None,
-            game_over_observer: None,
-        }
+    fn create(tonality: Tonality, seed: u64) -> State {
+        let exercise = EXERCISES.first().cloned().unwrap();
+        State::new(tonality, exercise, seed)
     }
 
-    pub fn new_shared(config: Config) -> SharedController {
-        let ctrl = Controller::new(config);
-        Rc::new(RefCell::new(ctrl))
+    fn question(&self) -> Option<Note> {
+        self.note
     }
 
-    pub fn new_game(&mut self, tonality: Tonality) {
-        let exercise = EXERCISES.first().cloned().unwrap();
-        let state = State::new(tonality, exercise);
-        self.new_game_with_state(state);
-
-        let new_game = NewGame {
-            tonality: tonality.to_string(),
-        };
-
-        let conn = establish_connection();
-        diesel::insert_into(octave_games::table)
-            .values(&new_game)
-            .execute(&conn)
-            .expect("Failed to save a game");
+    fn next_question(&mut self) -> Option<Note> {
+        self.next_note()
     }
 
-    fn new_game_with_state(&mut self, state: State) {
-        self.state = Some(state);
-        self.count_changed();
+    fn check_answer(&self, note: Note, answer: &Pitch) -> bool {
+        note.pitch.is_enharmonic(*answer)
     }
 
-    pub fn load_game(&mut self) -> Option<GameState> {
-        let conn = establish_connection();
-        let game = Controller::current_game()?;
-
-        let game_state = GameState::belonging_to(&game)
-            .first::<GameState>(&conn)
-            .optional()
-            .unwrap();
-
-        if let Some(ref s) = game_state {
-            let state = State::load(s);
-            self.new_game_with_state(state);
-        }
-
-        game_state
+    fn next_exercise(&mut self) -> Option<&'static Exercise> {
+        State::next_exercise(self)
     }
 
-    pub fn finish_game(&self) -> Game {
-        let conn = establish_connection();
-        let game = Controller::current_game().unwrap();
-
-        diesel::update(octave_games::table)
-            .set(octave_games::finished_at.eq(Utc::now().naive_utc()))
-            .filter(octave_games::id.eq(game.id))
-            .execute(&conn)
-            .unwrap();
-
-        octave_games::table.find(game.id).get_result(&conn).unwrap()
+    fn is_passed(&self) -> bool {
+        State::is_passed(self)
     }
 
-    pub fn save_state(&self) {
-        if let Some(ref state) = self.state {
-            let conn = establish_connection();
-            let game = Controller::current_game().unwrap();
-
-            let game_state = GameState::belonging_to(&game)
-                .first::<GameState>(&conn)
-                .optional()
-                .unwrap();
-            match game_state {
-                Some(current_state) => {
-                    let changeset = state.changeset();
-
-                    diesel::update(&current_state)
-                        .set(&changeset)
-                        .execute(&conn)
-                        .unwrap();
-                }
-                None => {
-                    let changeset = state.changeset();
-                    let new_state = NewGameState {
-                        tonality: state.tonality.to_string(),
-                        exercise: changeset.exercise,
-                        note: changeset.note,
-                        notes: changeset.notes,
-                        right_count: changeset.right_count,
-                        total_count: changeset.total_count,
-                        game_id: game.id,
-                    };
-
-                    diesel::insert_into(octave_game_states::table)
-                        .values(&new_state)
-                        .execute(&conn)
-                        .unwrap();
-                }
-            }
-        }
+    fn is_finished(&self) -> bool {
+        self.notes.is_empty()
     }
 
-    fn current_game() -> Option<Game> {
-        let conn = establish_connection();
-        octave_games::table
-            .filter(octave_games::finished_at.is_null())
-            .order(octave_games::created_at.desc())
-            .first::<Game>(&conn)
-            .optional()
-            .unwrap()
+    fn notes(&self) -> Vec<Note> {
+        self.notes.clone()
     }
-}
 
-impl Controller {
-    pub fn check_answers(&mut self, answers: &[&str]) -> Option<bool> {
-        match self.state {
-            Some(ref mut s) => {
-                if s.attempts_left == 0 {
-                    return None;
-                }
-                s.attempts_left -= 1;
-            }
-            None => unreachable!(),
-        }
-
-        let res = answers.iter().any(|a| self.check_answer(a));
-        Some(res)
+    fn score(&self) -> &Score {
+        &self.score
     }
 
-    fn check_answer(&mut self, answer: &str) -> bool {
-        let answer = answer.parse::<Pitch>().unwrap();
+    fn score_mut(&mut self) -> &mut Score {
+        &mut self.score
+    }
 
-        match self.current_note() {
-            Some(note) => {
-                let right = note.pitch == answer;
-                if right {
-                    println!("Right!");
-                    self.inc_right_count();
-                } else {
-                    println!("Wrong!");
-                }
+    fn play(&self, note: Note, player: &AudioBackend) {
+        player.play_note(note);
+    }
 
-                right
-            }
-            None => unreachable!(),
-        }
+    fn play_intro(&self, player: &AudioBackend) {
+        player.play_cadence(self.tonality);
     }
 
-    fn play_sample(&self, sample: Sample) {
-        println!("{}\n", sample);
-        self.gramophone
-            .send(sample)
-            .expect("Failed to play a sample");
+    fn prepare(&mut self, conn: &SqliteConnection, config: &Config) -> QueryResult<()> {
+        let scheduler = Scheduler::load(conn, self.tonality, config.adaptive)?;
+        self.set_scheduler(scheduler);
+        Ok(())
     }
 
-    fn play_note(&self, note: Note) {
-        let sample_path = format!("{}/{}.ogg", NOTES_PATH.display(), note);
-        self.play_sample(sample_path);
+    fn answered(&mut self, record: &AnswerRecord<State>) {
+        self.answer_note(record.question, record.right, record.response_time);
     }
 
-    pub fn play_tonal_center(&self) {
-        if let Some(ref state) = self.state {
-            let sample_path = format!("{}/IIVVIPAC - {}.ogg", TONES_PATH.display(), state.tonality);
-            self.play_sample(sample_path);
-        }
+    fn insert(&self, conn: &SqliteConnection) -> QueryResult<()> {
+        let new_game = NewGame {
+            tonality: self.tonality.to_string(),
+            seed: self.seed as i64,
+        };
+
+        diesel::insert_into(octave_games::table)
+            .values(&new_game)
+            .execute(conn)
+            .map(|_| ())
     }
 
-    pub fn play_next_note(&mut self) {
-        let mut note = match self.state {
-            Some(ref mut s) => s.next_note(),
-            None => None,
+    fn load(conn: &SqliteConnection) -> QueryResult<Option<State>> {
+        let game = match current_game(conn)? {
+            Some(game) => game,
+            None => return Ok(None),
+        };
+
+        let game_state = GameState::belonging_to(&game)
+            .first::<GameState>(conn)
+            .optional()?;
+
+        let game_state = match game_state {
+            Some(game_state) => game_state,
+            None => return Ok(None),
         };
 
-        if note.is_none() {
-            // Looks like this exercise is over, try to unlock the next one
-            if let Some(ref mut s) = self.state {
-                if s.total_count == s.right_count {
-                    let exercise = s.next_exercise();
-                    if let Some(ref observer) = self.next_exercise_observer {
-                        if let Some(exercise) = exercise {
-                            observer(exercise);
-                        }
-                    }
-                    note = s.next_note();
-                }
+        match State::load(&game_state, game.seed as u64) {
+            Some(state) => Ok(Some(state)),
+            None => {
+                // The teacher renumbered or removed the exercise
+                // or the state is broken, a new game starts instead
+                Self::finish(conn)?;
+                Ok(None)
             }
         }
+    }
+
+    fn save(&self, conn: &SqliteConnection) -> QueryResult<()> {
+        let game = match current_game(conn)? {
+            Some(game) => game,
+            None => return Ok(()),
+        };
 
-        match note {
-            Some(n) => {
-                println!("NEXT NOTE: {:?}", n);
-                self.play_note(n);
-                self.inc_total_count();
-                self.grant_attempts();
+        let game_state = GameState::belonging_to(&game)
+            .first::<GameState>(conn)
+            .optional()?;
+        match game_state {
+            Some(current_state) => {
+                let changeset = self.changeset();
+
+                diesel::update(&current_state)
+                    .set(&changeset)
+                    .execute(conn)?;
             }
             None => {
-                if let Some(ref observer) = self.game_over_observer {
-                    self.save_state();
-                    self.finish_game();
-
-                    observer();
-                }
+                let changeset = self.changeset();
+                let new_state = NewGameState {
+                    tonality: self.tonality.to_string(),
+                    exercise: changeset.exercise,
+                    note: changeset.note,
+                    notes: changeset.notes,
+                    right_count: changeset.right_count,
+                    total_count: changeset.total_count,
+                    game_id: game.id,
+                    draws: changeset.draws,
+                    exercise_right_count: changeset.exercise_right_count,
+                    exercise_total_count: changeset.exercise_total_count,
+                };
+
+                diesel::insert_into(octave_game_states::table)
+                    .values(&new_state)
+                    .execute(conn)?;
             }
         }
-    }
 
-    pub fn repeat_note(&self) {
-        self.current_note().map(|note| {
-            println!("REPEAT NOTE: {:?}", note);
-            self.play_note(note)
-        });
+        Ok(())
     }
 
-    fn current_note(&self) -> Option<Note> {
-        match self.state {
-            Some(ref state) => state.note,
-            None => None,
+    fn finish(conn: &SqliteConnection) -> QueryResult<()> {
+        if let Some(game) = current_game(conn)? {
+            diesel::update(octave_games::table)
+                .set(octave_games::finished_at.eq(Utc::now().naive_utc()))
+                .filter(octave_games::id.eq(game.id))
+                .execute(conn)?;
         }
-    }
-}
 
-impl Controller {
-    pub fn add_count_observer<F>(&mut self, f: F)
-    where
-        F: Fn(&Controller) -> () + 'static,
-    {
-        self.count_observers.push(Box::new(f));
+        Ok(())
     }
 
-    fn count_changed(&self) {
-        for f in &self.count_observers {
-            f(self)
-        }
-    }
+    fn log_answer(&self, conn: &SqliteConnection, record: &AnswerRecord<State>) -> QueryResult<()> {
+        let game = match current_game(conn)? {
+            Some(game) => game,
+            None => return Ok(()),
+        };
 
-    pub fn right_count(&self) -> u8 {
-        match self.state {
-            Some(ref state) => state.right_count,
-            None => 0,
-        }
-    }
+        let time = record.response_time;
+        let new_answer = NewAnswer {
+            game_id: game.id,
+            exercise: self.exercise.num as i32,
+            note: record.question.to_string(),
+            answer: record.answer.to_string(),
+            correct: record.right,
+            response_ms: (time.as_secs() * 1000 + time.subsec_nanos() as u64 / 1_000_000) as i32,
+            repeats: record.repeats as i32,
+        };
 
-    fn inc_right_count(&mut self) {
-        if let Some(ref mut s) = self.state {
-            s.right_count += 1;
-        }
-        self.count_changed();
-    }
+        // The game takes the new level of the note after the log,
+        // the answer and the level are kept together or not at all
+        let mut scheduler = self.scheduler.clone();
+        scheduler.answer(record.question, record.right, record.response_time);
 
-    pub fn total_count(&self) -> u8 {
-        match self.state {
-            Some(ref state) => state.total_count,
-            None => 0,
-        }
-    }
+        conn.transaction(|| {
+            diesel::insert_into(octave_answers::table)
+                .values(&new_answer)
+                .execute(conn)?;
 
-    fn inc_total_count(&mut self) {
-        if let Some(ref mut s) = self.state {
-            s.total_count += 1;
-        }
-        self.count_changed();
+            scheduler.save(conn, self.tonality, record.question)
+        })
     }
 }
 
-impl Controller {
-    fn grant_attempts(&mut self) {
-        if let Some(ref mut s) = self.state {
-            s.attempts_left = 1;
-        }
-    }
-
-    pub fn is_finished(&self) -> bool {
-        match self.state {
-            Some(ref state) => state.notes.is_empty(),
-            None => true,
-        }
-    }
+fn current_game(conn: &SqliteConnection) -> QueryResult<Option<models::Game>> {
+    octave_games::table
+        .filter(octave_games::finished_at.is_null())
+        .order(octave_games::created_at.desc())
+        .first::<models::Game>(conn)
+        .optional()
 }
@@ -1,5 +1,5 @@
 use chrono::NaiveDateTime;
-use schema::{octave_game_states, octave_games};
+use schema::{octave_answers, octave_game_states, octave_games, octave_memory};
 
 #[derive(Identifiable, Queryable)]
 #[table_name = "octave_games"]
@@ -8,12 +8,15 @@ pub struct Game {
     pub tonality: String,
     pub created_at: NaiveDateTime,
     pub finished_at: Option<NaiveDateTime>,
+    // Bits of the `u64` seed of the questions
+    pub seed: i64,
 }
 
 #[derive(Insertable)]
 #[table_name = "octave_games"]
 pub struct NewGame {
     pub tonality: String,
+    pub seed: i64,
 }
 
 #[derive(Associations, Identifiable, Queryable)]
@@ -28,6 +31,11 @@ pub struct GameState {
     pub right_count: i32,
     pub total_count: i32,
     pub game_id: i32,
+    // Random numbers drawn for the questions since the seed
+    pub draws: i64,
+    // Answers in the current exercise
+    pub exercise_right_count: i32,
+    pub exercise_total_count: i32,
 }
 
 #[derive(Insertable)]
@@ -40,6 +48,9 @@ pub struct NewGameState {
     pub right_count: i32,
     pub total_count: i32,
     pub game_id: i32,
+    pub draws: i64,
+    pub exercise_right_count: i32,
+    pub exercise_total_count: i32,
 }
 
 #[derive(AsChangeset)]
@@ -50,4 +61,52 @@ pub struct GameStateChangeset {
     pub notes: String,
     pub right_count: i32,
     pub total_count: i32,
+    pub draws: i64,
+    pub exercise_right_count: i32,
+    pub exercise_total_count: i32,
+}
+
+#[derive(Associations, Identifiable, Queryable)]
+#[belongs_to(Game)]
+#[table_name = "octave_answers"]
+pub struct Answer {
+    pub id: i32,
+    pub game_id: i32,
+    pub exercise: i32,
+    pub note: String,
+    // Pitch of the answer
+    pub answer: String,
+    pub correct: bool,
+    pub response_ms: i32,
+    pub repeats: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "octave_answers"]
+pub struct NewAnswer {
+    pub game_id: i32,
+    pub exercise: i32,
+    pub note: String,
+    pub answer: String,
+    pub correct: bool,
+    pub response_ms: i32,
+    pub repeats: i32,
+}
+
+#[derive(Queryable)]
+pub struct Memory {
+    pub tonality: String,
+    pub note: String,
+    pub level: i32,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "octave_memory"]
+pub struct NewMemory {
+    pub tonality: String,
+    pub note: String,
+    pub level: i32,
+    pub updated_at: NaiveDateTime,
 }
@@ -1,90 +1,117 @@
 use std::{fmt, str};
 
 lazy_static! {
-    pub static ref GAMUTS: Vec<Gamut> = {
+    pub static ref MODES: Vec<Mode> = {
         let mut v = Vec::new();
 
-        v.push(Gamut {
-            key: Pitch::C,
-            scale: [Pitch::C, Pitch::D, Pitch::E, Pitch::F, Pitch::G, Pitch::A, Pitch::B],
-        });
-        v.push(Gamut {
-            key: Pitch::G,
-            scale: [Pitch::G, Pitch::A, Pitch::B, Pitch::C, Pitch::D, Pitch::E, Pitch::Fsharp],
-        });
-        v.push(Gamut {
-            key: Pitch::D,
-            scale: [Pitch::D, Pitch::E, Pitch::Fsharp, Pitch::G, Pitch::A, Pitch::B, Pitch::Csharp],
-        });
-        v.push(Gamut {
-            key: Pitch::A,
-            scale: [Pitch::A, Pitch::B, Pitch::Csharp, Pitch::D, Pitch::E, Pitch::Fsharp,
-                    Pitch::Gsharp],
-        });
-        v.push(Gamut {
-            key: Pitch::E,
-            scale: [Pitch::E, Pitch::Fsharp, Pitch::Gsharp, Pitch::A, Pitch::B, Pitch::Csharp,
-                    Pitch::Dsharp],
-        });
-        v.push(Gamut {
-            key: Pitch::B,
-            scale: [Pitch::B, Pitch::Csharp, Pitch::Dsharp, Pitch::E, Pitch::Fsharp, Pitch::Gsharp,
-                    Pitch::Asharp],
-        });
-
-        v.push(Gamut {
-            key: Pitch::F,
-            scale: [Pitch::F, Pitch::G, Pitch::A, Pitch::Bflat, Pitch::C, Pitch::D, Pitch::E],
-        });
-        v.push(Gamut {
-            key: Pitch::Bflat,
-            scale: [Pitch::Bflat, Pitch::C, Pitch::D, Pitch::Eflat, Pitch::F, Pitch::G, Pitch::A],
-        });
-        v.push(Gamut {
-            key: Pitch::Eflat,
-            scale: [Pitch::Eflat, Pitch::F, Pitch::G, Pitch::Aflat, Pitch::Bflat, Pitch::C,
-                    Pitch::D],
-        });
-        v.push(Gamut {
-            key: Pitch::Aflat,
-            scale: [Pitch::Aflat, Pitch::Bflat, Pitch::C, Pitch::Dflat, Pitch::Eflat, Pitch::F,
-                    Pitch::G],
-        });
-        v.push(Gamut {
-            key: Pitch::Dflat,
-            scale: [Pitch::Dflat, Pitch::Eflat, Pitch::F, Pitch::Gflat, Pitch::Aflat, Pitch::Bflat,
-                    Pitch::C],
-        });
-        v.push(Gamut {
-            key: Pitch::Fsharp,
-            scale: [Pitch::Fsharp, Pitch::Gsharp, Pitch::Asharp, Pitch::B, Pitch::Csharp,
-                    Pitch::Dsharp, Pitch::F],
-        });
+        v.push(Mode::Major);
+        v.push(Mode::NaturalMinor);
+        v.push(Mode::HarmonicMinor);
+        v.push(Mode::MelodicMinor);
+        v.push(Mode::Dorian);
+        v.push(Mode::Phrygian);
+        v.push(Mode::Lydian);
+        v.push(Mode::Mixolydian);
+        v.push(Mode::Locrian);
 
         v
     };
 
+    static ref MAJOR_KEYS: Vec<Pitch> = {
+        let mut v = Vec::new();
+
+        v.extend(circle_of_fifths(Pitch::C, 6));
+        v.extend(circle_of_fourths(Pitch::F, 5));
+        v.push(Pitch::Fsharp);
+
+        v
+    };
+
+    // Every mode is built on the same degree of the major keys above,
+    // a tonic whose scale needs double accidentals is spelled enharmonically
     pub static ref TONALITIES: Vec<Tonality> = {
         let mut v = Vec::new();
 
-        v.push(Tonality(Pitch::C));
-        v.push(Tonality(Pitch::G));
-        v.push(Tonality(Pitch::D));
-        v.push(Tonality(Pitch::A));
-        v.push(Tonality(Pitch::E));
-        v.push(Tonality(Pitch::B));
+        for mode in MODES.iter() {
+            for key in MAJOR_KEYS.iter() {
+                let tonic = Gamut::major(*key).unwrap().scale[mode.relative_degree()];
+                let tonic = Some(tonic)
+                    .into_iter()
+                    .chain(tonic.enharmonic())
+                    .find(|t| Gamut::new(*t, &mode.formula()).is_some());
 
-        v.push(Tonality(Pitch::F));
-        v.push(Tonality(Pitch::Bflat));
-        v.push(Tonality(Pitch::Eflat));
-        v.push(Tonality(Pitch::Aflat));
-        v.push(Tonality(Pitch::Dflat));
-        v.push(Tonality(Pitch::Fsharp));
+                if let Some(tonic) = tonic {
+                    v.push(Tonality::new(tonic, *mode));
+                }
+            }
+        }
 
         v
     };
 }
 
+// Semitone steps between adjacent scale degrees
+pub type Formula = [u8; 7];
+
+pub const MAJOR: Formula = [2, 2, 1, 2, 2, 2, 1];
+
+// Natural pitches in semitones above C
+const NATURALS: [i8; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+// Spelling of the pitch classes without flats
+const SHARPS: [Pitch; 12] = [
+    Pitch::C,
+    Pitch::Csharp,
+    Pitch::D,
+    Pitch::Dsharp,
+    Pitch::E,
+    Pitch::F,
+    Pitch::Fsharp,
+    Pitch::G,
+    Pitch::Gsharp,
+    Pitch::A,
+    Pitch::Asharp,
+    Pitch::B,
+];
+
+// Names of the semitones above the key by the degrees of its major scale
+const CHROMATIC_DEGREES: [(u8, i8); 12] = [
+    (1, 0),
+    (2, -1),
+    (2, 0),
+    (3, -1),
+    (3, 0),
+    (4, 0),
+    (4, 1),
+    (5, 0),
+    (6, -1),
+    (6, 0),
+    (7, -1),
+    (7, 0),
+];
+
+// Walk the circle from the key to the dominant of its major scale
+fn circle_of_fifths(key: Pitch, count: usize) -> Vec<Pitch> {
+    walk_circle(key, 4, count)
+}
+
+// Walk the circle from the key to the subdominant of its major scale
+fn circle_of_fourths(key: Pitch, count: usize) -> Vec<Pitch> {
+    walk_circle(key, 3, count)
+}
+
+fn walk_circle(key: Pitch, degree: usize, count: usize) -> Vec<Pitch> {
+    let mut v = Vec::new();
+    let mut key = key;
+
+    for _ in 0..count {
+        v.push(key);
+        key = Gamut::major(key).expect("No gamut for this key").scale[degree];
+    }
+
+    v
+}
+
 // Represent a pitch from a particular octave.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Note {
@@ -97,7 +124,7 @@ impl str::FromStr for Note {
 
     fn from_str(s: &str) -> Result<Note, ()> {
         let chars = &s.chars().collect::<Vec<_>>()[..];
-        let (octave_str, pitch_str) = chars.split_last().unwrap();
+        let (octave_str, pitch_str) = chars.split_last().ok_or(())?;
 
         let octave = octave_str.to_string().parse::<Octave>()?;
         let pitch = pitch_str.into_iter().collect::<String>().parse::<Pitch>()?;
@@ -112,6 +139,67 @@ impl fmt::Display for Note {
     }
 }
 
+impl Note {
+    // Move the note by the number of letter names and semitones,
+    // e.g. a major third up is (2, 4) and a minor second down is (-1, -1).
+    // Returns `None` when the note leaves the octave range or would need
+    // a double accidental.
+    pub fn transpose(&self, steps: i8, semitones: i8) -> Option<Note> {
+        let octave = self.octave as i8;
+        let letters = octave * 7 + self.pitch.letter() as i8 + steps;
+        let semitone = octave * 12 + self.pitch.semitone() + semitones;
+
+        let octave = Octave::from_number(letters / 7)?;
+        let pitch = Pitch::spell((letters % 7) as usize, semitone - octave as i8 * 12)?;
+
+        Some(Note { octave, pitch })
+    }
+
+    // MIDI number of the sounding note, C4 is 60. Enharmonic notes
+    // share it, e.g. B#3 and C4.
+    pub fn midi(&self) -> u8 {
+        ((self.octave as i8 + 1) * 12 + self.pitch.semitone()) as u8
+    }
+
+    // Note of the MIDI number spelled with a natural or a sharp.
+    // Returns `None` out of the octave range.
+    pub fn from_midi(midi: u8) -> Option<Note> {
+        let octave = Octave::from_number((midi / 12) as i8 - 1)?;
+        let pitch = SHARPS[(midi % 12) as usize];
+
+        Some(Note { octave, pitch })
+    }
+
+    // The same sound spelled with the pitch, e.g. C4 as B#3.
+    // Returns `None` if the pitch sounds different.
+    pub fn respell(&self, pitch: Pitch) -> Option<Note> {
+        if !self.pitch.is_enharmonic(pitch) {
+            return None;
+        }
+
+        let octave = Octave::from_number((self.midi() as i8 - pitch.semitone()) / 12 - 1)?;
+        Some(Note { octave, pitch })
+    }
+
+    // The note and the same sound spelled with the enharmonic pitch,
+    // e.g. C#4 and Db4
+    pub fn spellings(&self) -> Vec<Note> {
+        let mut notes = vec![*self];
+        notes.extend(self.pitch.enharmonic().and_then(|p| self.respell(p)));
+        notes
+    }
+
+    // Semitones up to the other note, negative if it is lower
+    pub fn semitones_to(&self, other: Note) -> i8 {
+        other.midi() as i8 - self.midi() as i8
+    }
+
+    // Equal temperament frequency in Hz tuned to A4 = 440
+    pub fn frequency(&self) -> f32 {
+        440.0 * 2f32.powf((self.midi() as f32 - 69.0) / 12.0)
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Octave {
     Great = 2,
@@ -139,6 +227,18 @@ impl str::FromStr for Octave {
 }
 
 impl Octave {
+    pub fn from_number(number: i8) -> Option<Octave> {
+        match number {
+            2 => Some(Octave::Great),
+            3 => Some(Octave::Small),
+            4 => Some(Octave::First),
+            5 => Some(Octave::Second),
+            6 => Some(Octave::Third),
+            7 => Some(Octave::Fourth),
+            _ => None,
+        }
+    }
+
     pub fn next(&self) -> Option<Octave> {
         match *self {
             Octave::Great => Some(Octave::Small),
@@ -153,21 +253,261 @@ impl Octave {
 
 type Scale = [Pitch; 7];
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mode {
+    Major,
+    NaturalMinor,
+    HarmonicMinor,
+    MelodicMinor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+}
+
+impl Mode {
+    pub fn formula(&self) -> Formula {
+        match *self {
+            Mode::Major => MAJOR,
+            Mode::NaturalMinor => [2, 1, 2, 2, 1, 2, 2],
+            Mode::HarmonicMinor => [2, 1, 2, 2, 1, 3, 1],
+            // Ascending form, the descending one is the natural minor
+            Mode::MelodicMinor => [2, 1, 2, 2, 2, 2, 1],
+            Mode::Dorian => [2, 1, 2, 2, 2, 1, 2],
+            Mode::Phrygian => [1, 2, 2, 2, 1, 2, 2],
+            Mode::Lydian => [2, 2, 2, 1, 2, 2, 1],
+            Mode::Mixolydian => [2, 2, 1, 2, 2, 1, 2],
+            Mode::Locrian => [1, 2, 2, 1, 2, 2, 2],
+        }
+    }
+
+    pub fn tonalities(&self) -> Vec<Tonality> {
+        TONALITIES
+            .iter()
+            .filter(|t| t.mode == *self)
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_minor(&self) -> bool {
+        match *self {
+            Mode::Major | Mode::Lydian | Mode::Mixolydian => false,
+            _ => true,
+        }
+    }
+
+    // Degree of the relative major scale the mode starts on
+    fn relative_degree(&self) -> usize {
+        match *self {
+            Mode::Major => 0,
+            Mode::Dorian => 1,
+            Mode::Phrygian => 2,
+            Mode::Lydian => 3,
+            Mode::Mixolydian => 4,
+            Mode::NaturalMinor | Mode::HarmonicMinor | Mode::MelodicMinor => 5,
+            Mode::Locrian => 6,
+        }
+    }
+
+    fn suffix(&self) -> &'static str {
+        match *self {
+            Mode::Major => "maj",
+            Mode::NaturalMinor => "min",
+            Mode::HarmonicMinor => "hmin",
+            Mode::MelodicMinor => "mmin",
+            Mode::Dorian => "dor",
+            Mode::Phrygian => "phr",
+            Mode::Lydian => "lyd",
+            Mode::Mixolydian => "mix",
+            Mode::Locrian => "loc",
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Mode::Major => "Major",
+            Mode::NaturalMinor => "Natural minor",
+            Mode::HarmonicMinor => "Harmonic minor",
+            Mode::MelodicMinor => "Melodic minor",
+            Mode::Dorian => "Dorian",
+            Mode::Phrygian => "Phrygian",
+            Mode::Lydian => "Lydian",
+            Mode::Mixolydian => "Mixolydian",
+            Mode::Locrian => "Locrian",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
-pub struct Tonality(pub Pitch);
+pub struct Tonality {
+    pub key: Pitch,
+    pub mode: Mode,
+}
+
+impl Tonality {
+    pub fn new(key: Pitch, mode: Mode) -> Tonality {
+        Tonality { key, mode }
+    }
+
+    pub fn major(key: Pitch) -> Tonality {
+        Tonality::new(key, Mode::Major)
+    }
+
+    pub fn gamut(&self) -> Gamut {
+        Gamut::new(self.key, &self.mode.formula()).expect("No gamut for this tonality")
+    }
+
+    // Scale degree of the pitch counting from 1 for the key,
+    // `None` if the pitch is out of the scale
+    pub fn degree(&self, pitch: Pitch) -> Option<u8> {
+        self.gamut()
+            .scale
+            .iter()
+            .position(|&p| p == pitch)
+            .map(|i| i as u8 + 1)
+    }
+
+    // Pitch of the altered degree spelled with the letter of the degree,
+    // or with a neighbouring one if it would need a double accidental
+    pub fn altered(&self, degree: AlteredDegree) -> Pitch {
+        let letter = (self.key.letter() + degree.degree as usize - 1) % 7;
+        let semitone = self.key.semitone() + degree.semitones();
+
+        Pitch::spell(letter, semitone)
+            .or_else(|| Pitch::spell((letter + 1) % 7, semitone))
+            .or_else(|| Pitch::spell((letter + 6) % 7, semitone))
+            .expect("No spelling for the degree")
+    }
+
+    // Degree of the sound above the key named by the major scale,
+    // e.g. b3 for both Eb and D# in C
+    pub fn altered_degree(&self, pitch: Pitch) -> AlteredDegree {
+        let semitones = (pitch.pitch_class() + 12 - self.key.pitch_class()) % 12;
+        let (degree, alteration) = CHROMATIC_DEGREES[semitones as usize];
+        AlteredDegree::new(degree, alteration)
+    }
+
+    // Notes out of the scale by their degrees, e.g. b2, b3, #4, b6
+    // and b7 in a major key
+    pub fn chromatic(&self) -> Vec<(AlteredDegree, Pitch)> {
+        let gamut = self.gamut();
+
+        CHROMATIC_DEGREES
+            .iter()
+            .map(|&(degree, alteration)| AlteredDegree::new(degree, alteration))
+            .map(|degree| (degree, self.altered(degree)))
+            .filter(|&(_, pitch)| !gamut.scale.iter().any(|p| p.is_enharmonic(pitch)))
+            .collect()
+    }
+
+    // The tonality and the same one from the enharmonic key,
+    // e.g. F#maj and Gbmaj
+    pub fn spellings(&self) -> Vec<Tonality> {
+        let mut tonalities = vec![*self];
+        tonalities.extend(
+            self.key
+                .enharmonic()
+                .filter(|key| Gamut::new(*key, &self.mode.formula()).is_some())
+                .map(|key| Tonality::new(key, self.mode)),
+        );
+        tonalities
+    }
+
+    // Tonality whose cadence establishes this one. There are only major
+    // and minor cadences, so a mode borrows the one with the same third.
+    pub fn cadence(&self) -> Tonality {
+        let mode = if self.mode.is_minor() {
+            Mode::NaturalMinor
+        } else {
+            Mode::Major
+        };
+
+        Tonality::new(self.key, mode)
+    }
+}
+
+// Degree of the major scale of the key raised or lowered by a semitone,
+// e.g. b3 or #4, so the notes out of any mode are named the same way
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct AlteredDegree {
+    pub degree: u8,
+    pub alteration: i8,
+}
+
+impl AlteredDegree {
+    pub fn new(degree: u8, alteration: i8) -> AlteredDegree {
+        AlteredDegree { degree, alteration }
+    }
+
+    // Semitones above the key
+    fn semitones(&self) -> i8 {
+        let steps = &MAJOR[..self.degree as usize - 1];
+        steps.iter().map(|&s| s as i8).sum::<i8>() + self.alteration
+    }
+}
+
+impl str::FromStr for AlteredDegree {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<AlteredDegree, ()> {
+        let (alteration, degree) = if s.starts_with('b') {
+            (-1, &s[1..])
+        } else if s.starts_with('#') {
+            (1, &s[1..])
+        } else {
+            (0, s)
+        };
+
+        match degree.parse::<u8>() {
+            Ok(degree) if degree >= 1 && degree <= 7 => Ok(AlteredDegree::new(degree, alteration)),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for AlteredDegree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = match self.alteration {
+            -1 => "b",
+            1 => "#",
+            _ => "",
+        };
+
+        write!(f, "{}{}", sign, self.degree)
+    }
+}
 
 impl str::FromStr for Tonality {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Tonality, ()> {
-        let pitch = s[..s.len() - 3].parse::<Pitch>()?;
-        Ok(Tonality(pitch))
+        for mode in MODES.iter() {
+            if !s.ends_with(mode.suffix()) {
+                continue;
+            }
+
+            let key = match s[..s.len() - mode.suffix().len()].parse::<Pitch>() {
+                Ok(key) => key,
+                Err(_) => continue,
+            };
+
+            return Gamut::new(key, &mode.formula())
+                .map(|_| Tonality::new(key, *mode))
+                .ok_or(());
+        }
+
+        Err(())
     }
 }
 
 impl fmt::Display for Tonality {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}maj", self.0)
+        write!(f, "{}{}", self.key, self.mode.suffix())
     }
 }
 
@@ -177,6 +517,29 @@ pub struct Gamut {
     pub scale: Scale,
 }
 
+impl Gamut {
+    // Build a scale from the key by the formula, spelling each degree
+    // with the next letter name. Returns `None` if a degree would need
+    // a double sharp or a double flat.
+    pub fn new(key: Pitch, formula: &Formula) -> Option<Gamut> {
+        let mut scale = [key; 7];
+        let mut offset = 0;
+
+        for degree in 1..scale.len() {
+            offset += formula[degree - 1] as i8;
+
+            let letter = (key.letter() + degree) % 7;
+            scale[degree] = Pitch::spell(letter, key.semitone() + offset)?;
+        }
+
+        Some(Gamut { key, scale })
+    }
+
+    pub fn major(key: Pitch) -> Option<Gamut> {
+        Gamut::new(key, &MAJOR)
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Pitch {
     Cflat,
@@ -202,6 +565,110 @@ pub enum Pitch {
     Bsharp,
 }
 
+impl Pitch {
+    fn from_parts(letter: usize, alteration: i8) -> Option<Pitch> {
+        let pitch = match (letter, alteration) {
+            (0, -1) => Pitch::Cflat,
+            (0, 0) => Pitch::C,
+            (0, 1) => Pitch::Csharp,
+            (1, -1) => Pitch::Dflat,
+            (1, 0) => Pitch::D,
+            (1, 1) => Pitch::Dsharp,
+            (2, -1) => Pitch::Eflat,
+            (2, 0) => Pitch::E,
+            (2, 1) => Pitch::Esharp,
+            (3, -1) => Pitch::Fflat,
+            (3, 0) => Pitch::F,
+            (3, 1) => Pitch::Fsharp,
+            (4, -1) => Pitch::Gflat,
+            (4, 0) => Pitch::G,
+            (4, 1) => Pitch::Gsharp,
+            (5, -1) => Pitch::Aflat,
+            (5, 0) => Pitch::A,
+            (5, 1) => Pitch::Asharp,
+            (6, -1) => Pitch::Bflat,
+            (6, 0) => Pitch::B,
+            (6, 1) => Pitch::Bsharp,
+            _ => return None,
+        };
+
+        Some(pitch)
+    }
+
+    // Spell the semitone above C with the letter
+    fn spell(letter: usize, semitone: i8) -> Option<Pitch> {
+        let mut alteration = (semitone - NATURALS[letter]) % 12;
+        if alteration > 6 {
+            alteration -= 12;
+        } else if alteration < -6 {
+            alteration += 12;
+        }
+
+        Pitch::from_parts(letter, alteration)
+    }
+
+    // The same sound spelled with the neighbouring letter,
+    // so sharps become flats and flats become sharps
+    fn enharmonic(&self) -> Option<Pitch> {
+        let letter = if self.alteration() > 0 {
+            (self.letter() + 1) % 7
+        } else {
+            (self.letter() + 6) % 7
+        };
+
+        Pitch::spell(letter, self.semitone())
+    }
+
+    // Semitones above C in the octave whatever the spelling,
+    // e.g. 1 for both C# and Db, 0 for B#
+    pub fn pitch_class(&self) -> u8 {
+        ((self.semitone() + 12) % 12) as u8
+    }
+
+    // The same sound whatever the spelling, e.g. C# and Db
+    pub fn is_enharmonic(&self, other: Pitch) -> bool {
+        self.pitch_class() == other.pitch_class()
+    }
+
+    // Index of the letter name, C is 0 and B is 6
+    fn letter(&self) -> usize {
+        match *self {
+            Pitch::Cflat | Pitch::C | Pitch::Csharp => 0,
+            Pitch::Dflat | Pitch::D | Pitch::Dsharp => 1,
+            Pitch::Eflat | Pitch::E | Pitch::Esharp => 2,
+            Pitch::Fflat | Pitch::F | Pitch::Fsharp => 3,
+            Pitch::Gflat | Pitch::G | Pitch::Gsharp => 4,
+            Pitch::Aflat | Pitch::A | Pitch::Asharp => 5,
+            Pitch::Bflat | Pitch::B | Pitch::Bsharp => 6,
+        }
+    }
+
+    fn alteration(&self) -> i8 {
+        match *self {
+            Pitch::Cflat
+            | Pitch::Dflat
+            | Pitch::Eflat
+            | Pitch::Fflat
+            | Pitch::Gflat
+            | Pitch::Aflat
+            | Pitch::Bflat => -1,
+            Pitch::Csharp
+            | Pitch::Dsharp
+            | Pitch::Esharp
+            | Pitch::Fsharp
+            | Pitch::Gsharp
+            | Pitch::Asharp
+            | Pitch::Bsharp => 1,
+            _ => 0,
+        }
+    }
+
+    // Semitones above C of the same octave, so Cb is -1 and B# is 12
+    fn semitone(&self) -> i8 {
+        NATURALS[self.letter()] + self.alteration()
+    }
+}
+
 impl str::FromStr for Pitch {
     type Err = ();
 
@@ -284,8 +751,228 @@ mod tests {
         let c_ton = TONALITIES.first().unwrap();
         assert_eq!(c_ton.to_string(), "Cmaj");
 
-        let fsharp_ton = TONALITIES.last().unwrap();
+        let fsharp_ton = Mode::Major.tonalities().last().cloned().unwrap();
         assert_eq!(fsharp_ton.to_string(), "F#maj");
+
+        assert_eq!(Tonality::new(Pitch::A, Mode::NaturalMinor).to_string(), "Amin");
+        assert_eq!(Tonality::new(Pitch::A, Mode::HarmonicMinor).to_string(), "Ahmin");
+        assert_eq!(Tonality::new(Pitch::Bflat, Mode::MelodicMinor).to_string(), "Bbmmin");
+        assert_eq!(Tonality::new(Pitch::D, Mode::Dorian).to_string(), "Ddor");
+    }
+
+    fn scale(s: &str) -> Scale {
+        let pitches = s
+            .split_whitespace()
+            .map(|p| p.parse::<Pitch>().unwrap())
+            .collect::<Vec<_>>();
+
+        let mut scale = [Pitch::C; 7];
+        scale.copy_from_slice(&pitches);
+        scale
+    }
+
+    #[test]
+    fn major_scales() {
+        let scales = [
+            "C D E F G A B",
+            "G A B C D E F#",
+            "D E F# G A B C#",
+            "A B C# D E F# G#",
+            "E F# G# A B C# D#",
+            "B C# D# E F# G# A#",
+            "F# G# A# B C# D# E#",
+            "C# D# E# F# G# A# B#",
+            "F G A Bb C D E",
+            "Bb C D Eb F G A",
+            "Eb F G Ab Bb C D",
+            "Ab Bb C Db Eb F G",
+            "Db Eb F Gb Ab Bb C",
+            "Gb Ab Bb Cb Db Eb F",
+            "Cb Db Eb Fb Gb Ab Bb",
+        ];
+
+        for s in scales.iter() {
+            let expected = scale(s);
+            let gamut = Gamut::major(expected[0]).unwrap();
+
+            assert_eq!(gamut.key, expected[0]);
+            assert_eq!(gamut.scale, expected, "{} major", expected[0]);
+        }
+    }
+
+    #[test]
+    fn scale_degrees() {
+        let tonality = Tonality::major(Pitch::D);
+        assert_eq!(tonality.degree(Pitch::D), Some(1));
+        assert_eq!(tonality.degree(Pitch::G), Some(4));
+        assert_eq!(tonality.degree(Pitch::Csharp), Some(7));
+        assert_eq!(tonality.degree(Pitch::C), None);
+        assert_eq!(tonality.degree(Pitch::Dflat), None);
+    }
+
+    #[test]
+    fn pitch_classes() {
+        assert_eq!(Pitch::C.pitch_class(), 0);
+        assert_eq!(Pitch::Csharp.pitch_class(), 1);
+        assert_eq!(Pitch::Dflat.pitch_class(), 1);
+        assert_eq!(Pitch::Bsharp.pitch_class(), 0);
+        assert_eq!(Pitch::Cflat.pitch_class(), 11);
+    }
+
+    #[test]
+    fn midi_numbers() {
+        assert_eq!("C4".parse::<Note>().unwrap().midi(), 60);
+        assert_eq!("A4".parse::<Note>().unwrap().midi(), 69);
+        assert_eq!("B#3".parse::<Note>().unwrap().midi(), 60);
+        assert_eq!("Cb4".parse::<Note>().unwrap().midi(), 59);
+        assert_eq!("E2".parse::<Note>().unwrap().midi(), 40);
+
+        assert_eq!(Note::from_midi(61), "C#4".parse().ok());
+        assert_eq!(Note::from_midi(59), "B3".parse().ok());
+        assert_eq!(Note::from_midi(35), None);
+        assert_eq!(Note::from_midi(108), None);
+
+        for midi in 36..108 {
+            assert_eq!(Note::from_midi(midi).unwrap().midi(), midi);
+        }
+    }
+
+    #[test]
+    fn respell_notes() {
+        let note = "C4".parse::<Note>().unwrap();
+        assert_eq!(note.respell(Pitch::Bsharp), "B#3".parse().ok());
+        assert_eq!(note.respell(Pitch::C), Some(note));
+        assert_eq!(note.respell(Pitch::Csharp), None);
+
+        let note = "B3".parse::<Note>().unwrap();
+        assert_eq!(note.respell(Pitch::Cflat), "Cb4".parse().ok());
+    }
+
+    #[test]
+    fn semitone_distance() {
+        let c4 = "C4".parse::<Note>().unwrap();
+        assert_eq!(c4.semitones_to("E4".parse().unwrap()), 4);
+        assert_eq!(c4.semitones_to("B#3".parse().unwrap()), 0);
+        assert_eq!(c4.semitones_to("A3".parse().unwrap()), -3);
+        assert_eq!(c4.semitones_to("C5".parse().unwrap()), 12);
+    }
+
+    #[test]
+    fn enharmonic_pitches() {
+        assert!(Pitch::Csharp.is_enharmonic(Pitch::Dflat));
+        assert!(Pitch::Bsharp.is_enharmonic(Pitch::C));
+        assert!(Pitch::Cflat.is_enharmonic(Pitch::B));
+        assert!(Pitch::E.is_enharmonic(Pitch::E));
+        assert!(!Pitch::E.is_enharmonic(Pitch::Esharp));
+    }
+
+    #[test]
+    fn altered_degrees() {
+        let degrees = ["b3", "#4", "5"]
+            .iter()
+            .map(|s| s.parse::<AlteredDegree>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(degrees[0], AlteredDegree::new(3, -1));
+        assert_eq!(degrees[1].to_string(), "#4");
+        assert!("b8".parse::<AlteredDegree>().is_err());
+        assert!("x3".parse::<AlteredDegree>().is_err());
+
+        let tonality = Tonality::major(Pitch::D);
+        assert_eq!(tonality.altered(degrees[0]), Pitch::F);
+        assert_eq!(tonality.altered(degrees[1]), Pitch::Gsharp);
+        assert_eq!(tonality.altered(degrees[2]), Pitch::A);
+
+        // Bbb would need a double flat in Db
+        let tonality = Tonality::major(Pitch::Dflat);
+        assert_eq!(tonality.altered(degrees[0]), Pitch::Fflat);
+        assert_eq!(tonality.altered(AlteredDegree::new(6, -1)), Pitch::A);
+
+        let tonality = Tonality::new(Pitch::C, Mode::NaturalMinor);
+        assert_eq!(tonality.altered_degree(Pitch::Eflat), degrees[0]);
+        assert_eq!(tonality.altered_degree(Pitch::Dsharp), degrees[0]);
+        assert_eq!(tonality.altered_degree(Pitch::Fsharp), degrees[1]);
+        assert_eq!(tonality.altered_degree(Pitch::G), degrees[2]);
+        assert_eq!(tonality.altered_degree(Pitch::Bsharp), AlteredDegree::new(1, 0));
+    }
+
+    #[test]
+    fn chromatic_notes() {
+        let names = |tonality: Tonality| {
+            tonality
+                .chromatic()
+                .iter()
+                .map(|&(degree, pitch)| format!("{}={}", degree, pitch))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(Tonality::major(Pitch::C)),
+            vec!["b2=Db", "b3=Eb", "#4=F#", "b6=Ab", "b7=Bb"]
+        );
+        assert_eq!(
+            names(Tonality::new(Pitch::A, Mode::NaturalMinor)),
+            vec!["b2=Bb", "3=C#", "#4=D#", "6=F#", "7=G#"]
+        );
+    }
+
+    #[test]
+    fn major_scale_needs_double_accidentals() {
+        assert!(Gamut::major(Pitch::Dsharp).is_none());
+        assert!(Gamut::major(Pitch::Fflat).is_none());
+    }
+
+    #[test]
+    fn tonalities_circle() {
+        let keys = Mode::Major
+            .tonalities()
+            .iter()
+            .map(|t| t.key)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            keys,
+            vec![
+                Pitch::C,
+                Pitch::G,
+                Pitch::D,
+                Pitch::A,
+                Pitch::E,
+                Pitch::B,
+                Pitch::F,
+                Pitch::Bflat,
+                Pitch::Eflat,
+                Pitch::Aflat,
+                Pitch::Dflat,
+                Pitch::Fsharp,
+            ]
+        );
+    }
+
+    #[test]
+    fn transpose_note() {
+        let note = |s: &str| s.parse::<Note>().unwrap();
+
+        assert_eq!(note("C4").transpose(2, 4), Some(note("E4")));
+        assert_eq!(note("B4").transpose(1, 1), Some(note("C5")));
+        assert_eq!(note("E4").transpose(-2, -3), Some(note("C#4")));
+        assert_eq!(note("F#4").transpose(3, 6), Some(note("B#4")));
+        assert_eq!(note("C4").transpose(-1, -1), Some(note("B3")));
+        assert_eq!(note("Db4").transpose(7, 12), Some(note("Db5")));
+
+        // Eb double flat
+        assert_eq!(note("Db4").transpose(-6, -11), None);
+        assert_eq!(note("C2").transpose(-1, -1), None);
+    }
+
+    #[test]
+    fn note_frequency() {
+        let freq = |s: &str| s.parse::<Note>().unwrap().frequency();
+
+        assert_eq!(freq("A4"), 440.0);
+        assert_eq!(freq("A2"), 110.0);
+        assert!((freq("C4") - 261.63).abs() < 0.01);
+        assert_eq!(freq("B#3"), freq("C4"));
+        assert_eq!(freq("Cb5"), freq("B4"));
     }
 
     #[test]
@@ -312,9 +999,74 @@ mod tests {
     #[test]
     fn parse_tonality_from_str() {
         let tonality: Tonality = "Cmaj".parse().unwrap();
-        assert_eq!(tonality, Tonality(Pitch::C));
+        assert_eq!(tonality, Tonality::major(Pitch::C));
 
         let tonality: Tonality = "C#maj".parse().unwrap();
-        assert_eq!(tonality, Tonality(Pitch::Csharp));
+        assert_eq!(tonality, Tonality::major(Pitch::Csharp));
+
+        let tonality: Tonality = "C#min".parse().unwrap();
+        assert_eq!(tonality, Tonality::new(Pitch::Csharp, Mode::NaturalMinor));
+
+        let tonality: Tonality = "Ebhmin".parse().unwrap();
+        assert_eq!(tonality, Tonality::new(Pitch::Eflat, Mode::HarmonicMinor));
+
+        let tonality: Tonality = "Ammin".parse().unwrap();
+        assert_eq!(tonality, Tonality::new(Pitch::A, Mode::MelodicMinor));
+
+        let tonality: Tonality = "Gmix".parse().unwrap();
+        assert_eq!(tonality, Tonality::new(Pitch::G, Mode::Mixolydian));
+
+        assert!("D#hmin".parse::<Tonality>().is_err());
+        assert!("Hmaj".parse::<Tonality>().is_err());
+    }
+
+    #[test]
+    fn mode_scales() {
+        let cases = [
+            ("Amin", "A B C D E F G"),
+            ("Ahmin", "A B C D E F G#"),
+            ("Ammin", "A B C D E F# G#"),
+            ("Ebhmin", "Eb F Gb Ab Bb Cb D"),
+            ("Ddor", "D E F G A B C"),
+            ("Ephr", "E F G A B C D"),
+            ("Flyd", "F G A B C D E"),
+            ("Gmix", "G A B C D E F"),
+            ("Bloc", "B C D E F G A"),
+            ("F#dor", "F# G# A B C# D# E"),
+        ];
+
+        for &(tonality, expected) in cases.iter() {
+            let tonality = tonality.parse::<Tonality>().unwrap();
+            assert_eq!(tonality.gamut().scale, scale(expected), "{}", tonality);
+        }
+    }
+
+    #[test]
+    fn mode_tonalities() {
+        let minors = Mode::NaturalMinor.tonalities();
+        assert_eq!(minors.len(), 12);
+        assert_eq!(minors[0], Tonality::new(Pitch::A, Mode::NaturalMinor));
+
+        let dorians = Mode::Dorian.tonalities();
+        assert_eq!(dorians[0], Tonality::new(Pitch::D, Mode::Dorian));
+
+        // G# and D# harmonic minor need double sharps
+        let harmonic = Mode::HarmonicMinor.tonalities();
+        assert_eq!(harmonic.len(), 12);
+        assert!(harmonic.contains(&Tonality::new(Pitch::Aflat, Mode::HarmonicMinor)));
+        assert!(harmonic.contains(&Tonality::new(Pitch::Eflat, Mode::HarmonicMinor)));
+        assert!(!harmonic.contains(&Tonality::new(Pitch::Dsharp, Mode::HarmonicMinor)));
+    }
+
+    #[test]
+    fn tonality_cadence() {
+        let d_dor = Tonality::new(Pitch::D, Mode::Dorian);
+        assert_eq!(d_dor.cadence(), Tonality::new(Pitch::D, Mode::NaturalMinor));
+
+        let g_mix = Tonality::new(Pitch::G, Mode::Mixolydian);
+        assert_eq!(g_mix.cadence(), Tonality::major(Pitch::G));
+
+        let a_hmin = Tonality::new(Pitch::A, Mode::HarmonicMinor);
+        assert_eq!(a_hmin.cadence(), Tonality::new(Pitch::A, Mode::NaturalMinor));
     }
 }
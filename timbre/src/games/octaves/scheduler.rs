@@ -0,0 +1,273 @@
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::note::{Note, Tonality};
+use games::octaves::models::{Memory, NewMemory};
+use schema::octave_memory;
+
+// Leitner boxes a note moves through, from the weakest
+pub const LEVELS: u8 = 5;
+
+// A right answer that took longer doesn't promote the note
+pub const SLOW_ANSWER: u64 = 3;
+
+// An exercise is over after this many questions per note
+// even if some notes are still weak
+pub const QUESTIONS_PER_NOTE: usize = 3;
+
+// Remembers how well every note of a tonality is known. The database
+// belongs to a single user, so the memory is the memory of the user.
+#[derive(Clone, Debug, Default)]
+pub struct Scheduler {
+    // Repeat the weak notes instead of asking each note once
+    adaptive: bool,
+    // Leitner box of the notes, the unknown ones are in the first
+    levels: BTreeMap<Note, u8>,
+    // Questions asked in the current exercise
+    asked: usize,
+    limit: usize,
+}
+
+impl Scheduler {
+    pub fn new(adaptive: bool) -> Scheduler {
+        Scheduler {
+            adaptive,
+            ..Scheduler::default()
+        }
+    }
+
+    pub fn load(
+        conn: &SqliteConnection,
+        tonality: Tonality,
+        adaptive: bool,
+    ) -> QueryResult<Scheduler> {
+        let memory = octave_memory::table
+            .filter(octave_memory::tonality.eq(tonality.to_string()))
+            .load::<Memory>(conn)?;
+
+        let mut scheduler = Scheduler::new(adaptive);
+        for m in memory {
+            if let Ok(note) = m.note.parse::<Note>() {
+                // The levels out of the boxes would break the weights
+                let level = m.level.max(1).min(LEVELS as i32);
+                scheduler.levels.insert(note, level as u8);
+            }
+        }
+
+        Ok(scheduler)
+    }
+
+    // Keep the level of the note for the next games
+    pub fn save(&self, conn: &SqliteConnection, tonality: Tonality, note: Note) -> QueryResult<()> {
+        let memory = NewMemory {
+            tonality: tonality.to_string(),
+            note: note.to_string(),
+            level: self.level(note) as i32,
+            updated_at: Utc::now().naive_utc(),
+        };
+
+        diesel::replace_into(octave_memory::table)
+            .values(&memory)
+            .execute(conn)
+            .map(|_| ())
+    }
+
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive
+    }
+
+    pub fn level(&self, note: Note) -> u8 {
+        self.levels.get(&note).cloned().unwrap_or(1)
+    }
+
+    // Start counting the questions of an exercise from the ones
+    // already asked, e.g. before the game was saved.
+    // It's over after the limit.
+    pub fn restart(&mut self, limit: usize, asked: usize) {
+        self.asked = asked;
+        self.limit = limit;
+    }
+
+    // Pick one of the notes, the weaker the note the more likely.
+    // Doesn't ask the last note twice in a row if there are others.
+    // Returns `None` when the exercise is over.
+    pub fn choose<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        notes: &[Note],
+        last: Option<Note>,
+    ) -> Option<Note> {
+        if self.asked >= self.limit {
+            return None;
+        }
+
+        let candidates = notes
+            .iter()
+            .cloned()
+            .filter(|&n| notes.len() == 1 || Some(n) != last)
+            .collect::<Vec<_>>();
+
+        let weight = |note: Note| 1u32 << (LEVELS - self.level(note));
+        let total = candidates.iter().map(|&n| weight(n)).sum::<u32>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut pos = rng.gen_range(0, total);
+        for &note in candidates.iter() {
+            if pos < weight(note) {
+                self.asked += 1;
+                return Some(note);
+            }
+            pos -= weight(note);
+        }
+
+        None
+    }
+
+    // Move the note to another box. Returns `true` if the note is known
+    // well enough to leave the exercise, i.e. answered right and fast.
+    pub fn answer(&mut self, note: Note, right: bool, response_time: Duration) -> bool {
+        let level = self.level(note);
+        let fast = response_time < Duration::from_secs(SLOW_ANSWER);
+
+        let level = match (right, fast) {
+            (false, _) => 1,
+            (true, false) => level,
+            (true, true) => (level + 1).min(LEVELS),
+        };
+        self.levels.insert(note, level);
+
+        right && fast
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::note::{Octave, Pitch};
+    use rand::{Isaac64Rng, SeedableRng};
+    use Database;
+
+    fn note(pitch: Pitch) -> Note {
+        Note {
+            octave: Octave::First,
+            pitch,
+        }
+    }
+
+    #[test]
+    fn move_between_levels() {
+        let mut scheduler = Scheduler::new(true);
+        let c = note(Pitch::C);
+        let fast = Duration::from_millis(800);
+        let slow = Duration::from_secs(5);
+
+        assert_eq!(scheduler.level(c), 1);
+        assert!(scheduler.answer(c, true, fast));
+        assert_eq!(scheduler.level(c), 2);
+        assert!(!scheduler.answer(c, true, slow));
+        assert_eq!(scheduler.level(c), 2);
+
+        for _ in 0..10 {
+            scheduler.answer(c, true, fast);
+        }
+        assert_eq!(scheduler.level(c), LEVELS);
+
+        assert!(!scheduler.answer(c, false, fast));
+        assert_eq!(scheduler.level(c), 1);
+    }
+
+    #[test]
+    fn ask_weak_notes_more_often() {
+        let mut scheduler = Scheduler::new(true);
+        let c = note(Pitch::C);
+        let d = note(Pitch::D);
+        let e = note(Pitch::E);
+        for _ in 0..LEVELS {
+            scheduler.answer(c, true, Duration::from_secs(1));
+            scheduler.answer(d, true, Duration::from_secs(1));
+        }
+
+        let notes = [c, d, e];
+        scheduler.restart(1000, 0);
+        let mut rng = Isaac64Rng::from_seed(&[1]);
+        let weak = (0..300)
+            .filter(|_| scheduler.choose(&mut rng, &notes, None) == Some(e))
+            .count();
+
+        // 16 of 18 on average
+        assert!(weak > 200, "{} of 300", weak);
+    }
+
+    #[test]
+    fn stop_after_the_limit() {
+        let mut scheduler = Scheduler::new(true);
+        let notes = [note(Pitch::C), note(Pitch::D)];
+        scheduler.restart(notes.len() * QUESTIONS_PER_NOTE, 0);
+
+        let mut rng = Isaac64Rng::from_seed(&[1]);
+        let mut last = None;
+        for _ in 0..notes.len() * QUESTIONS_PER_NOTE {
+            let next = scheduler.choose(&mut rng, &notes, last);
+            assert!(next.is_some());
+            assert_ne!(next, last);
+            last = next;
+        }
+
+        assert_eq!(scheduler.choose(&mut rng, &notes, last), None);
+    }
+
+    #[test]
+    fn remember_levels() {
+        let db = Database::in_memory().unwrap();
+        let tonality = Tonality::major(Pitch::C);
+        let c = note(Pitch::C);
+
+        let mut scheduler = Scheduler::load(db.connection(), tonality, true).unwrap();
+        scheduler.answer(c, true, Duration::from_secs(1));
+        scheduler.answer(c, true, Duration::from_secs(1));
+        scheduler.save(db.connection(), tonality, c).unwrap();
+        scheduler.answer(c, false, Duration::from_secs(1));
+        scheduler.save(db.connection(), tonality, c).unwrap();
+        scheduler.answer(c, true, Duration::from_secs(1));
+        scheduler.save(db.connection(), tonality, c).unwrap();
+
+        let scheduler = Scheduler::load(db.connection(), tonality, true).unwrap();
+        assert_eq!(scheduler.level(c), 2);
+
+        let other = Scheduler::load(db.connection(), Tonality::major(Pitch::G), true).unwrap();
+        assert_eq!(other.level(c), 1);
+    }
+
+    #[test]
+    fn clamp_loaded_levels() {
+        let db = Database::in_memory().unwrap();
+        let tonality = Tonality::major(Pitch::C);
+        let (c, d) = (note(Pitch::C), note(Pitch::D));
+
+        for &(note, level) in [(c, 9), (d, -3)].iter() {
+            diesel::insert_into(octave_memory::table)
+                .values(&NewMemory {
+                    tonality: tonality.to_string(),
+                    note: note.to_string(),
+                    level,
+                    updated_at: Utc::now().naive_utc(),
+                })
+                .execute(db.connection())
+                .unwrap();
+        }
+
+        let mut scheduler = Scheduler::load(db.connection(), tonality, true).unwrap();
+        assert_eq!(scheduler.level(c), LEVELS);
+        assert_eq!(scheduler.level(d), 1);
+
+        scheduler.restart(10, 0);
+        let mut rng = Isaac64Rng::from_seed(&[1]);
+        assert!(scheduler.choose(&mut rng, &[c, d], None).is_some());
+    }
+}
@@ -1,8 +1,12 @@
-use rand::{thread_rng, Rng};
-use games::octaves::{Exercise, EXERCISES};
+use rand::Rng;
+use std::time::Duration;
+use games::octaves::{exercise, Exercise};
 
-use super::note::{Gamut, Note, Tonality, GAMUTS};
+use super::note::{Note, Tonality};
+use super::scheduler::{Scheduler, QUESTIONS_PER_NOTE};
 use games::octaves::models::{GameState, GameStateChangeset};
+use games::replay::ReplayRng;
+use games::Score;
 
 #[derive(Debug)]
 pub struct State {
@@ -14,53 +18,74 @@ pub struct State {
     pub note: Option<Note>,
     // Notes to play
     pub notes: Vec<Note>,
-    // Right answered count
-    pub right_count: u8,
-    // Total played count
-    pub total_count: u8,
-    // Answer attempts
-    pub attempts_left: u8,
+    // Answers count
+    pub score: Score,
+    // Answers count in the current exercise
+    pub exercise_score: Score,
+    // Levels of the notes, chooses the notes in the adaptive mode
+    pub scheduler: Scheduler,
+    // The same seed gives the same questions
+    pub seed: u64,
+    rng: ReplayRng,
 }
 
 impl State {
-    pub fn new(tonality: Tonality, exercise: Exercise) -> State {
+    pub fn new(tonality: Tonality, exercise: Exercise, seed: u64) -> State {
         let mut state = State {
             tonality,
             exercise,
             note: None,
             notes: vec![],
-            right_count: 0,
-            total_count: 0,
-            attempts_left: 0,
+            score: Score::default(),
+            exercise_score: Score::default(),
+            scheduler: Scheduler::default(),
+            seed,
+            rng: ReplayRng::new(seed, 0),
         };
 
         state.generate_notes();
         state
     }
 
-    pub fn load(game_state: &GameState) -> State {
-        let tonality = game_state.tonality.parse::<Tonality>().unwrap();
-        let exercise = EXERCISES
-            .iter()
-            .find(|&ex| ex.num == game_state.exercise as u8)
-            .cloned()
-            .unwrap();
-        let note = game_state.note.parse::<Note>().unwrap();
+    // A loaded game goes on with the same questions as if it had
+    // never stopped, the drawn numbers are replayed from the seed.
+    // Returns `None` if the exercise is gone from the curriculum
+    // or the state can't be read.
+    pub fn load(game_state: &GameState, seed: u64) -> Option<State> {
+        let tonality = game_state.tonality.parse::<Tonality>().ok()?;
+        let exercise = exercise::find(game_state.exercise as u8).cloned()?;
+        // Empty if the game was saved before the first question
+        let note = match game_state.note.as_str() {
+            "" => None,
+            note => Some(note.parse::<Note>().ok()?),
+        };
         let notes = game_state
             .notes
             .split(",")
-            .map(|s| s.parse::<Note>().unwrap())
-            .collect::<Vec<_>>();
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<Note>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        let score = Score {
+            right_count: game_state.right_count as u32,
+            total_count: game_state.total_count as u32,
+        };
+        let exercise_score = Score {
+            right_count: game_state.exercise_right_count as u32,
+            total_count: game_state.exercise_total_count as u32,
+        };
 
-        State {
+        Some(State {
             tonality,
             exercise,
-            note: Some(note),
+            note,
             notes,
-            right_count: game_state.right_count as u8,
-            total_count: game_state.total_count as u8,
-            attempts_left: 0,
-        }
+            score,
+            exercise_score,
+            scheduler: Scheduler::default(),
+            seed,
+            rng: ReplayRng::new(seed, game_state.draws as u64),
+        })
     }
 
     // TODO: implement
@@ -75,72 +100,174 @@ impl State {
                 .map(|n| n.to_string())
                 .collect::<Vec<_>>()
                 .join(","),
-            right_count: self.right_count as i32,
-            total_count: self.total_count as i32,
+            right_count: self.score.right_count as i32,
+            total_count: self.score.total_count as i32,
+            draws: self.rng.draws() as i64,
+            exercise_right_count: self.exercise_score.right_count as i32,
+            exercise_total_count: self.exercise_score.total_count as i32,
         }
     }
 
+    // The degrees of the exercise in every octave, the notes are
+    // repeated in random order if there are more questions
     fn generate_notes(&mut self) {
-        let gamut: &Gamut = GAMUTS
-            .iter()
-            .find(|g| g.key == self.tonality.0)
-            .expect("No gamut for this tonality");
-        let octaves = &self.exercise.octaves;
-
-        for octave in octaves.iter() {
-            for pitch in gamut.scale.iter() {
-                self.notes.push(Note {
+        let (mut notes, stressed) = self.exercise_notes();
+        // The stressed degrees are asked twice as often
+        notes.extend(stressed);
+
+        if let Some(count) = self.exercise.questions {
+            let mut questions = Vec::new();
+            while questions.len() < count {
+                let mut round = notes.clone();
+                self.rng.shuffle(&mut round);
+                questions.extend(round);
+            }
+            questions.truncate(count);
+            notes = questions;
+        }
+
+        self.notes = notes;
+        self.restart_scheduler();
+    }
+
+    // Every note of the exercise once and the stressed ones
+    fn exercise_notes(&self) -> (Vec<Note>, Vec<Note>) {
+        let gamut = self.tonality.gamut();
+        let chromatic = if self.exercise.chromatic {
+            self.tonality.chromatic()
+        } else {
+            Vec::new()
+        };
+        let mut notes = Vec::new();
+        let mut stressed = Vec::new();
+
+        for octave in self.exercise.octaves.iter() {
+            for &degree in self.exercise.degrees.iter() {
+                notes.push(Note {
                     octave: *octave,
-                    pitch: *pitch,
+                    pitch: gamut.scale[degree as usize - 1],
                 });
             }
 
+            for &(degree, pitch) in chromatic.iter() {
+                let note = Note {
+                    octave: *octave,
+                    pitch,
+                };
+                notes.push(note);
+                if self.exercise.stress.contains(&degree) {
+                    stressed.push(note);
+                }
+            }
+
             if let Some(next_octave) = octave.next() {
-                self.notes.push(Note {
-                    octave: next_octave,
-                    pitch: gamut.key,
-                });
+                if self.exercise.degrees.contains(&1) {
+                    notes.push(Note {
+                        octave: next_octave,
+                        pitch: gamut.key,
+                    });
+                }
             }
         }
 
-        self.notes.dedup();
+        notes.dedup();
+        (notes, stressed)
+    }
+
+    // Use the scheduler with the levels of the notes,
+    // the notes left in the exercise stay the same
+    pub fn set_scheduler(&mut self, scheduler: Scheduler) {
+        self.scheduler = scheduler;
+        if self.scheduler.is_adaptive() {
+            // The notes come back by the levels instead
+            self.notes.sort();
+            self.notes.dedup();
+        }
+        self.restart_scheduler();
+    }
+
+    // The limit comes from the whole exercise and the questions asked
+    // so far count, so a loaded game ends after as many questions
+    // as if it had never stopped
+    fn restart_scheduler(&mut self) {
+        let limit = self.exercise.questions.unwrap_or_else(|| {
+            let (mut notes, _) = self.exercise_notes();
+            notes.sort();
+            notes.dedup();
+            notes.len() * QUESTIONS_PER_NOTE
+        });
+        let asked = self.exercise_score.total_count as usize;
+        self.scheduler.restart(limit, asked);
     }
 
+    // In the adaptive mode the notes stay until they are answered right
+    // and the scheduler ends the exercise, otherwise each note is asked once
     pub fn next_note(&mut self) -> Option<Note> {
         println!("next note is called");
-        self.drop_note();
-        self.note = thread_rng().choose(&self.notes).map(|n| *n);
+        if self.scheduler.is_adaptive() {
+            self.note = self.scheduler.choose(&mut self.rng, &self.notes, self.note);
+            if self.note.is_none() {
+                self.notes.clear();
+            }
+        } else {
+            self.drop_note();
+            self.note = self.rng.choose(&self.notes).map(|n| *n);
+        }
+
+        if self.note.is_some() {
+            self.exercise_score.total_count += 1;
+        }
         self.note
     }
 
+    // Update the level of the note, a known note leaves
+    // the adaptive exercise
+    pub fn answer_note(&mut self, note: Note, right: bool, response_time: Duration) {
+        if right {
+            self.exercise_score.right_count += 1;
+        }
+
+        let known = self.scheduler.answer(note, right, response_time);
+        if known && self.scheduler.is_adaptive() {
+            self.notes.retain(|&n| n != note);
+        }
+    }
+
+    // A known note may have left the adaptive exercise already
     fn drop_note(&mut self) {
-        if let Some(note) = self.note {
-            let index = self.notes.iter().position(|&n| n == note);
-            self.notes.remove(index.unwrap());
+        let note = self.note;
+        if let Some(index) = self.notes.iter().position(|&n| Some(n) == note) {
+            self.notes.remove(index);
         }
     }
 
     pub fn next_exercise(&mut self) -> Option<&'static Exercise> {
-        let num = self.exercise.num + 1;
-        EXERCISES.iter().find(|&ex| ex.num == num).and_then(|ex| {
+        self.exercise.next().and_then(|ex| {
             self.exercise = ex.clone();
+            self.exercise_score = Score::default();
             self.generate_notes();
 
             Some(ex)
         })
     }
+
+    pub fn is_passed(&self) -> bool {
+        let score = self.exercise_score;
+        self.exercise.is_passed(score.right_count, score.total_count)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::super::note::{Octave, Pitch};
+    use super::super::note::{Mode, Octave, Pitch};
+    use games::Game;
 
     #[test]
     fn generate_notes_first_ex() {
-        let tonality = Tonality(Pitch::C);
-        let exercise = EXERCISES.iter().nth(0).cloned().unwrap();
-        let state = State::new(tonality, exercise);
+        let tonality = Tonality::major(Pitch::C);
+        let exercise = exercise::default_exercises()[0].clone();
+        let state = State::new(tonality, exercise, 1);
 
         let notes = [
             Note {
@@ -182,9 +309,9 @@ mod tests {
 
     #[test]
     fn generate_notes_second_ex() {
-        let tonality = Tonality(Pitch::C);
-        let exercise = EXERCISES.iter().nth(1).cloned().unwrap();
-        let state = State::new(tonality, exercise);
+        let tonality = Tonality::major(Pitch::C);
+        let exercise = exercise::default_exercises()[1].clone();
+        let state = State::new(tonality, exercise, 1);
 
         let notes = [
             Note {
@@ -252,11 +379,169 @@ mod tests {
         assert_eq!(state.notes, notes.to_vec());
     }
 
+    #[test]
+    fn generate_notes_harmonic_minor() {
+        let tonality = Tonality::new(Pitch::A, Mode::HarmonicMinor);
+        let exercise = exercise::default_exercises()[0].clone();
+        let state = State::new(tonality, exercise, 1);
+
+        let pitches = state.notes.iter().map(|n| n.pitch).collect::<Vec<_>>();
+        assert_eq!(
+            pitches,
+            vec![
+                Pitch::A,
+                Pitch::B,
+                Pitch::C,
+                Pitch::D,
+                Pitch::E,
+                Pitch::F,
+                Pitch::Gsharp,
+                Pitch::A,
+            ]
+        );
+        assert_eq!(state.notes.last().unwrap().octave, Octave::Second);
+    }
+
+    #[test]
+    fn generate_notes_chromatic() {
+        let tonality = Tonality::major(Pitch::C);
+        let exercise = Exercise {
+            chromatic: true,
+            stress: vec!["b3".parse().unwrap(), "#4".parse().unwrap(), "3".parse().unwrap()],
+            ..exercise::default_exercises()[0].clone()
+        };
+        let state = State::new(tonality, exercise, 1);
+
+        let pitches = state.notes.iter().map(|n| n.pitch).collect::<Vec<_>>();
+        assert_eq!(
+            pitches,
+            vec![
+                Pitch::C,
+                Pitch::D,
+                Pitch::E,
+                Pitch::F,
+                Pitch::G,
+                Pitch::A,
+                Pitch::B,
+                Pitch::Dflat,
+                Pitch::Eflat,
+                Pitch::Fsharp,
+                Pitch::Aflat,
+                Pitch::Bflat,
+                Pitch::C,
+                // The diatonic 3 isn't stressed
+                Pitch::Eflat,
+                Pitch::Fsharp,
+            ]
+        );
+    }
+
+    #[test]
+    fn enharmonic_answer() {
+        let state = State::create(Tonality::major(Pitch::Dflat), 1);
+        let note = Note {
+            octave: Octave::First,
+            pitch: Pitch::C,
+        };
+
+        assert!(state.check_answer(note, &Pitch::C));
+        assert!(state.check_answer(note, &Pitch::Bsharp));
+        assert!(!state.check_answer(note, &Pitch::Csharp));
+
+        let note = Note {
+            octave: Octave::First,
+            pitch: Pitch::Dflat,
+        };
+        assert!(state.check_answer(note, &Pitch::Csharp));
+        assert!(state.check_answer(note, &Pitch::Dflat));
+    }
+
+    // The state as it's saved and loaded again
+    fn reload(state: &State) -> State {
+        let changeset = state.changeset();
+        let game_state = GameState {
+            id: 1,
+            tonality: state.tonality.to_string(),
+            exercise: changeset.exercise,
+            note: changeset.note,
+            notes: changeset.notes,
+            right_count: changeset.right_count,
+            total_count: changeset.total_count,
+            game_id: 1,
+            draws: changeset.draws,
+            exercise_right_count: changeset.exercise_right_count,
+            exercise_total_count: changeset.exercise_total_count,
+        };
+
+        State::load(&game_state, state.seed).unwrap()
+    }
+
+    #[test]
+    fn load_exercise_score() {
+        let exercise = exercise::default_exercises()[1].clone();
+        let mut state = State::new(Tonality::major(Pitch::C), exercise, 1);
+        state.next_note();
+        state.score = Score {
+            right_count: 3,
+            total_count: 10,
+        };
+        state.exercise_score = Score {
+            right_count: 2,
+            total_count: 2,
+        };
+
+        let loaded = reload(&state);
+        assert_eq!(loaded.score.right_count, 3);
+        assert_eq!(loaded.score.total_count, 10);
+        assert_eq!(loaded.exercise_score.right_count, 2);
+        assert_eq!(loaded.exercise_score.total_count, 2);
+        assert!(loaded.is_passed());
+    }
+
+    #[test]
+    fn resume_adaptive_exercise() {
+        let exercise = exercise::default_exercises()[0].clone();
+        let mut state = State::new(Tonality::major(Pitch::C), exercise, 1);
+        state.set_scheduler(Scheduler::new(true));
+
+        // Wrong answers keep every note in the exercise
+        let mut asked = 0;
+        for _ in 0..5 {
+            let note = state.next_note().unwrap();
+            state.answer_note(note, false, Duration::from_secs(1));
+            asked += 1;
+        }
+
+        let mut loaded = reload(&state);
+        loaded.set_scheduler(Scheduler::new(true));
+        while let Some(note) = loaded.next_note() {
+            loaded.answer_note(note, false, Duration::from_secs(1));
+            asked += 1;
+        }
+
+        let notes = state.notes.len();
+        assert_eq!(asked, notes * QUESTIONS_PER_NOTE);
+    }
+
+    #[test]
+    fn count_many_questions() {
+        let exercise = exercise::parse("[[exercise]]\nnum = 1\noctaves = [4]\nquestions = 300")
+            .unwrap()
+            .remove(0);
+        let mut state = State::new(Tonality::major(Pitch::C), exercise, 1);
+
+        while let Some(note) = state.next_note() {
+            state.answer_note(note, true, Duration::from_secs(1));
+        }
+        assert_eq!(state.exercise_score.total_count, 300);
+        assert!(state.is_passed());
+    }
+
     #[test]
     fn next_note_first_ex() {
-        let tonality = Tonality(Pitch::C);
-        let exercise = EXERCISES.iter().nth(0).cloned().unwrap();
-        let mut state = State::new(tonality, exercise);
+        let tonality = Tonality::major(Pitch::C);
+        let exercise = exercise::default_exercises()[0].clone();
+        let mut state = State::new(tonality, exercise, 1);
 
         assert!(state.note.is_none());
 
@@ -318,9 +603,9 @@ mod tests {
 
     #[test]
     fn next_note_second_ex() {
-        let tonality = Tonality(Pitch::C);
-        let exercise = EXERCISES.iter().nth(1).cloned().unwrap();
-        let mut state = State::new(tonality, exercise);
+        let tonality = Tonality::major(Pitch::C);
+        let exercise = exercise::default_exercises()[1].clone();
+        let mut state = State::new(tonality, exercise, 1);
 
         assert!(state.note.is_none());
 
@@ -415,4 +700,68 @@ mod tests {
         notes.sort();
         assert_eq!(notes, expected_notes);
     }
+
+    fn questions(state: &mut State) -> Vec<Note> {
+        let mut notes = Vec::new();
+        while let Some(note) = state.next_note() {
+            notes.push(note);
+        }
+        notes
+    }
+
+    #[test]
+    fn same_questions_by_seed() {
+        let tonality = Tonality::major(Pitch::C);
+        let exercise = exercise::default_exercises()[1].clone();
+
+        let notes = questions(&mut State::new(tonality, exercise.clone(), 42));
+        assert_eq!(notes.len(), 15);
+        assert_eq!(questions(&mut State::new(tonality, exercise.clone(), 42)), notes);
+        assert_ne!(questions(&mut State::new(tonality, exercise, 43)), notes);
+    }
+
+    #[test]
+    fn adaptive_notes_stay_until_known() {
+        let tonality = Tonality::major(Pitch::C);
+        let exercise = exercise::default_exercises()[0].clone();
+        let mut state = State::new(tonality, exercise, 1);
+        state.set_scheduler(Scheduler::new(true));
+
+        let note = state.next_note().unwrap();
+        state.answer_note(note, false, Duration::from_secs(1));
+        assert!(state.notes.contains(&note));
+
+        let next_note = state.next_note().unwrap();
+        assert_ne!(next_note, note);
+        state.answer_note(next_note, true, Duration::from_secs(1));
+        assert!(!state.notes.contains(&next_note));
+        assert_eq!(state.notes.len(), 7);
+    }
+
+    #[test]
+    fn exercise_degrees_and_questions() {
+        let exercise = Exercise {
+            degrees: vec![1, 3, 5],
+            questions: Some(10),
+            pass: 0.5,
+            ..exercise::default_exercises()[0].clone()
+        };
+        let mut state = State::new(Tonality::major(Pitch::C), exercise, 1);
+        assert_eq!(state.notes.len(), 10);
+
+        let mut notes = state.notes.clone();
+        notes.sort();
+        notes.dedup();
+        assert_eq!(
+            notes.iter().map(|n| n.to_string()).collect::<Vec<_>>(),
+            vec!["C4", "E4", "G4", "C5"]
+        );
+
+        for i in 0..10 {
+            let note = state.next_note().unwrap();
+            state.answer_note(note, i % 2 == 0, Duration::from_secs(1));
+        }
+        assert!(state.next_note().is_none());
+        assert!(state.is_passed());
+    }
 }
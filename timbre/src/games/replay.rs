@@ -0,0 +1,37 @@
+use rand::{Isaac64Rng, Rng, SeedableRng};
+
+// Counts the numbers drawn from the seed, so a loaded game can draw
+// as many again and go on with the same questions
+#[derive(Debug)]
+pub struct ReplayRng {
+    rng: Isaac64Rng,
+    draws: u64,
+}
+
+impl ReplayRng {
+    pub fn new(seed: u64, draws: u64) -> ReplayRng {
+        let mut replay = ReplayRng {
+            rng: Isaac64Rng::from_seed(&[seed]),
+            draws: 0,
+        };
+        for _ in 0..draws {
+            replay.next_u64();
+        }
+        replay
+    }
+
+    pub fn draws(&self) -> u64 {
+        self.draws
+    }
+}
+
+impl Rng for ReplayRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws += 1;
+        self.rng.next_u64()
+    }
+}
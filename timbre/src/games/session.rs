@@ -0,0 +1,658 @@
+use rand::{thread_rng, Rng};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use config::{Config, Instrument};
+use database::Database;
+use error::Error;
+use games::{AnswerRecord, Game};
+use player::{AudioBackend, EarsBackend};
+
+// Drives any game: plays the questions, counts the answers
+// and keeps the game in the database
+pub struct Session<G: Game, B: AudioBackend = EarsBackend> {
+    config: Config,
+    db: Database,
+    player: B,
+    game: Option<G>,
+    // Answer attempts for the current question
+    attempts_left: u8,
+    // When the current question was played the first time
+    asked_at: Option<Instant>,
+    repeats: u32,
+    count_observers: Vec<Box<Fn(&Session<G, B>) -> ()>>,
+    pub next_exercise_observer: Option<Box<Fn(&'static G::Exercise) -> ()>>,
+    pub game_over_observer: Option<Box<Fn() -> ()>>,
+}
+
+pub type SharedSession<G, B = EarsBackend> = Rc<RefCell<Session<G, B>>>;
+
+impl<G: Game> Session<G> {
+    pub fn new(config: Config, db: Database) -> Session<G> {
+        let player = EarsBackend::new(config.sounds, config.instrument_for(G::NAME));
+        Session::with_backend(config, db, player)
+    }
+
+    pub fn new_shared(config: Config, db: Database) -> SharedSession<G> {
+        let session = Session::new(config, db);
+        Rc::new(RefCell::new(session))
+    }
+}
+
+impl<G: Game, B: AudioBackend> Session<G, B> {
+    pub fn with_backend(config: Config, db: Database, player: B) -> Session<G, B> {
+        Session {
+            config,
+            db,
+            player,
+            game: None,
+            attempts_left: 0,
+            asked_at: None,
+            repeats: 0,
+            count_observers: Vec::new(),
+            next_exercise_observer: None,
+            game_over_observer: None,
+        }
+    }
+
+    // Takes effect from the next question
+    pub fn set_instrument(&mut self, instrument: Instrument) {
+        self.player.set_instrument(instrument);
+    }
+
+    pub fn game(&self) -> Option<&G> {
+        self.game.as_ref()
+    }
+
+    // Takes the shared seed from the config if there is one,
+    // so everyone gets the same questions
+    pub fn new_game(&mut self, settings: G::Settings) -> Result<(), Error> {
+        let seed = self.config.seed.unwrap_or_else(|| thread_rng().gen());
+        self.new_game_with_seed(settings, seed)
+    }
+
+    // Fails if the exercise has notes the backend can't play,
+    // e.g. the ones outside the range of the sample pack
+    pub fn new_game_with_seed(&mut self, settings: G::Settings, seed: u64) -> Result<(), Error> {
+        let mut game = G::create(settings, seed);
+        check_range(&self.player, &game)?;
+
+        game.prepare(self.db.connection(), &self.config)?;
+        game.insert(self.db.connection())?;
+
+        self.new_game_with_state(game);
+        Ok(())
+    }
+
+    fn new_game_with_state(&mut self, game: G) {
+        self.game = Some(game);
+        self.attempts_left = 0;
+        self.count_changed();
+    }
+
+    // Returns `false` if there is no unfinished game
+    pub fn load_game(&mut self) -> Result<bool, Error> {
+        match G::load(self.db.connection())? {
+            Some(mut game) => {
+                check_range(&self.player, &game)?;
+                game.prepare(self.db.connection(), &self.config)?;
+                self.new_game_with_state(game);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn finish_game(&self) -> Result<(), Error> {
+        G::finish(self.db.connection())?;
+        Ok(())
+    }
+
+    pub fn save_state(&self) -> Result<(), Error> {
+        let game = self.game.as_ref().ok_or(Error::NoGame)?;
+        game.save(self.db.connection())?;
+        Ok(())
+    }
+}
+
+impl<G: Game, B: AudioBackend> Session<G, B> {
+    // `None` if the question is already answered
+    pub fn check_answer(&mut self, answer: &G::Answer) -> Result<Option<bool>, Error> {
+        let game = self.game.as_mut().ok_or(Error::NoGame)?;
+        let question = match game.question() {
+            Some(question) if self.attempts_left > 0 => question,
+            _ => return Ok(None),
+        };
+
+        let right = game.check_answer(question, answer);
+        let record = AnswerRecord {
+            question,
+            answer,
+            right,
+            response_time: self.asked_at
+                .map_or(Duration::from_secs(0), |at| at.elapsed()),
+            repeats: self.repeats,
+        };
+        // The game learns from the answer once it's logged,
+        // a failed log leaves the question to be answered again
+        game.log_answer(self.db.connection(), &record)?;
+        game.answered(&record);
+
+        self.attempts_left -= 1;
+        if right {
+            println!("Right!");
+            self.inc_right_count();
+        } else {
+            println!("Wrong!");
+        }
+
+        Ok(Some(right))
+    }
+
+    pub fn play_intro(&self) -> Result<(), Error> {
+        let game = self.game.as_ref().ok_or(Error::NoGame)?;
+        game.play_intro(&self.player);
+        Ok(())
+    }
+
+    fn play_question(&self, question: G::Question) -> Result<(), Error> {
+        let game = self.game.as_ref().ok_or(Error::NoGame)?;
+        game.play(question, &self.player);
+        Ok(())
+    }
+
+    // Fails with the reason if the game is over because the next
+    // exercise can't be played
+    pub fn play_next_question(&mut self) -> Result<(), Error> {
+        let mut question = match self.game {
+            Some(ref mut g) => g.next_question(),
+            None => return Err(Error::NoGame),
+        };
+        let mut stopped = None;
+
+        if question.is_none() {
+            // Looks like this exercise is over, try to unlock the next one
+            if let Some(ref mut g) = self.game {
+                if g.is_passed() {
+                    let exercise = g.next_exercise();
+                    if let Err(e) = check_range(&self.player, g) {
+                        stopped = Some(e);
+                    } else {
+                        if let Some(ref observer) = self.next_exercise_observer {
+                            if let Some(exercise) = exercise {
+                                observer(exercise);
+                            }
+                        }
+                        question = g.next_question();
+                    }
+                }
+            }
+        }
+
+        match question {
+            Some(q) => {
+                println!("NEXT QUESTION: {}", q);
+                self.player.next_instrument();
+                self.play_question(q)?;
+                self.asked_at = Some(Instant::now());
+                self.repeats = 0;
+                self.inc_total_count();
+                self.grant_attempts();
+            }
+            None => {
+                if let Some(ref observer) = self.game_over_observer {
+                    self.save_state()?;
+                    self.finish_game()?;
+
+                    observer();
+                }
+            }
+        }
+
+        match stopped {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    // Does nothing before the first question
+    pub fn repeat_question(&mut self) -> Result<(), Error> {
+        let question = self.game.as_ref().ok_or(Error::NoGame)?.question();
+        if let Some(question) = question {
+            println!("REPEAT QUESTION: {}", question);
+            self.play_question(question)?;
+            self.repeats += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<G: Game, B: AudioBackend> Session<G, B> {
+    pub fn add_count_observer<F>(&mut self, f: F)
+    where
+        F: Fn(&Session<G, B>) -> () + 'static,
+    {
+        self.count_observers.push(Box::new(f));
+    }
+
+    fn count_changed(&self) {
+        for f in &self.count_observers {
+            f(self)
+        }
+    }
+
+    pub fn right_count(&self) -> u32 {
+        match self.game {
+            Some(ref game) => game.score().right_count,
+            None => 0,
+        }
+    }
+
+    fn inc_right_count(&mut self) {
+        if let Some(ref mut g) = self.game {
+            g.score_mut().right_count += 1;
+        }
+        self.count_changed();
+    }
+
+    pub fn total_count(&self) -> u32 {
+        match self.game {
+            Some(ref game) => game.score().total_count,
+            None => 0,
+        }
+    }
+
+    fn inc_total_count(&mut self) {
+        if let Some(ref mut g) = self.game {
+            g.score_mut().total_count += 1;
+        }
+        self.count_changed();
+    }
+}
+
+impl<G: Game, B: AudioBackend> Session<G, B> {
+    fn grant_attempts(&mut self) {
+        self.attempts_left = 1;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.game {
+            Some(ref game) => game.is_finished(),
+            None => true,
+        }
+    }
+}
+
+fn check_range<G: Game, B: AudioBackend>(player: &B, game: &G) -> Result<(), Error> {
+    let unplayable = game.notes().into_iter().find(|&n| !player.can_play(n));
+
+    match unplayable {
+        Some(note) => Err(Error::OutOfRange(note)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt;
+    use games::octaves::note::{Note, Octave, Pitch, Tonality};
+    use games::octaves::State;
+    use player::{NullBackend, RecordingBackend, Request};
+
+    // Plays the first and the second octaves only
+    struct NarrowBackend;
+
+    impl AudioBackend for NarrowBackend {
+        fn play(&self, _request: Request) {}
+
+        fn can_play(&self, note: Note) -> bool {
+            note.octave == Octave::First || note.octave == Octave::Second
+        }
+    }
+
+    fn session(recorder: &RecordingBackend) -> Session<State, RecordingBackend> {
+        let db = Database::in_memory().unwrap();
+        let mut session = Session::with_backend(Config::default(), db, recorder.clone());
+        session.new_game_with_state(State::create(Tonality::major(Pitch::C), 0));
+        session
+    }
+
+    #[test]
+    fn play_and_repeat_question() {
+        let recorder = RecordingBackend::new();
+        let mut session = session(&recorder);
+
+        session.play_intro().unwrap();
+        session.play_next_question().unwrap();
+        session.repeat_question().unwrap();
+
+        let note = session.game().unwrap().question().unwrap();
+        assert_eq!(
+            recorder.requests(),
+            vec![
+                Request::Cadence(Tonality::major(Pitch::C)),
+                Request::Notes(vec![note]),
+                Request::Notes(vec![note]),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_answer_once_per_question() {
+        let recorder = RecordingBackend::new();
+        let mut session = session(&recorder);
+
+        assert_eq!(session.check_answer(&Pitch::C).unwrap(), None);
+
+        session.play_next_question().unwrap();
+        let note = session.game().unwrap().question().unwrap();
+
+        assert_eq!(session.check_answer(&note.pitch).unwrap(), Some(true));
+        assert_eq!(session.check_answer(&note.pitch).unwrap(), None);
+        assert_eq!(session.right_count(), 1);
+        assert_eq!(session.total_count(), 1);
+    }
+
+    #[test]
+    fn check_exercise_range() {
+        let mut game = State::create(Tonality::major(Pitch::C), 0);
+        assert!(check_range(&NarrowBackend, &game).is_ok());
+
+        // The second exercise goes up to the third octave
+        game.next_exercise();
+        match check_range(&NarrowBackend, &game) {
+            Err(Error::OutOfRange(note)) => assert_eq!(note.octave, Octave::Third),
+            res => panic!("Expected a note out of range, got {:?}", res),
+        }
+        assert!(check_range(&RecordingBackend::new(), &game).is_ok());
+    }
+
+    #[test]
+    fn stop_when_next_exercise_is_out_of_range() {
+        let db = Database::in_memory().unwrap();
+        let mut session = Session::<State, _>::with_backend(Config::default(), db, NarrowBackend);
+        session.new_game(Tonality::major(Pitch::C)).unwrap();
+        session.game_over_observer = Some(Box::new(|| {}));
+
+        let mut result = Ok(());
+        for _ in 0..20 {
+            result = session.play_next_question();
+            if result.is_err() {
+                break;
+            }
+            let note = session.game().unwrap().question().unwrap();
+            session.check_answer(&note.pitch).unwrap();
+        }
+
+        // The second exercise goes up to the third octave
+        match result {
+            Err(Error::OutOfRange(note)) => assert_eq!(note.octave, Octave::Third),
+            res => panic!("Expected a note out of range, got {:?}", res),
+        }
+        assert!(!session.load_game().unwrap());
+    }
+
+    #[test]
+    fn no_game_is_running() {
+        let db = Database::in_memory().unwrap();
+        let mut session =
+            Session::<State, _>::with_backend(Config::default(), db, NullBackend);
+
+        assert!(session.is_finished());
+        match session.check_answer(&Pitch::C) {
+            Err(Error::NoGame) => (),
+            res => panic!("Expected no game, got {:?}", res),
+        }
+        match session.play_next_question() {
+            Err(Error::NoGame) => (),
+            res => panic!("Expected no game, got {:?}", res),
+        }
+        assert!(session.play_intro().is_err());
+        assert!(session.repeat_question().is_err());
+        assert!(session.save_state().is_err());
+    }
+
+    #[test]
+    fn save_and_load_game() {
+        let db = Database::in_memory().unwrap();
+        let mut session =
+            Session::<State, _>::with_backend(Config::default(), db.clone(), NullBackend);
+        session.new_game(Tonality::major(Pitch::D)).unwrap();
+        session.play_next_question().unwrap();
+        let note = session.game().unwrap().question().unwrap();
+        session.check_answer(&note.pitch).unwrap();
+        session.save_state().unwrap();
+
+        // Another session sharing the connection picks the game up
+        let mut other =
+            Session::<State, _>::with_backend(Config::default(), db, NullBackend);
+        assert!(other.load_game().unwrap());
+        assert_eq!(other.game().unwrap().tonality, Tonality::major(Pitch::D));
+        assert_eq!(other.right_count(), 1);
+        assert_eq!(other.total_count(), 1);
+        assert_eq!(other.game().unwrap().seed, session.game().unwrap().seed);
+
+        other.finish_game().unwrap();
+        assert!(!session.load_game().unwrap());
+    }
+
+    #[test]
+    fn load_game_saved_before_first_question() {
+        use diesel::prelude::*;
+        use games::{chords, intervals};
+        use schema::octave_game_states;
+
+        let db = Database::in_memory().unwrap();
+        let mut session =
+            Session::<State, _>::with_backend(Config::default(), db.clone(), NullBackend);
+        session.new_game(Tonality::major(Pitch::D)).unwrap();
+        session.save_state().unwrap();
+
+        let mut other =
+            Session::<State, _>::with_backend(Config::default(), db.clone(), NullBackend);
+        assert!(other.load_game().unwrap());
+        assert_eq!(other.game().unwrap().question(), None);
+        other.play_next_question().unwrap();
+        assert!(other.game().unwrap().question().is_some());
+
+        let mut intervals = Session::<intervals::State, _>::with_backend(
+            Config::default(),
+            db.clone(),
+            NullBackend,
+        );
+        intervals.new_game(()).unwrap();
+        intervals.save_state().unwrap();
+        assert!(intervals.load_game().unwrap());
+
+        let mut chords =
+            Session::<chords::State, _>::with_backend(Config::default(), db.clone(), NullBackend);
+        chords.new_game(()).unwrap();
+        chords.save_state().unwrap();
+        assert!(chords.load_game().unwrap());
+
+        // A broken state starts a new game
+        diesel::update(octave_game_states::table)
+            .set(octave_game_states::note.eq("H9"))
+            .execute(db.connection())
+            .unwrap();
+        assert!(!session.load_game().unwrap());
+    }
+
+    #[test]
+    fn drop_game_of_missing_exercise() {
+        use diesel::prelude::*;
+        use schema::{octave_game_states, octave_games};
+
+        let db = Database::in_memory().unwrap();
+        let mut session =
+            Session::<State, _>::with_backend(Config::default(), db.clone(), NullBackend);
+        session.new_game(Tonality::major(Pitch::C)).unwrap();
+        session.play_next_question().unwrap();
+        session.save_state().unwrap();
+
+        // The teacher removed the exercise of the saved game
+        diesel::update(octave_game_states::table)
+            .set(octave_game_states::exercise.eq(99))
+            .execute(db.connection())
+            .unwrap();
+
+        assert!(!session.load_game().unwrap());
+        let unfinished = octave_games::table
+            .filter(octave_games::finished_at.is_null())
+            .count()
+            .get_result::<i64>(db.connection())
+            .unwrap();
+        assert_eq!(unfinished, 0);
+    }
+
+    #[test]
+    fn replay_loaded_game() {
+        let db = Database::in_memory().unwrap();
+        let mut session =
+            Session::<State, _>::with_backend(Config::default(), db.clone(), NullBackend);
+        session.new_game_with_seed(Tonality::major(Pitch::E), 11).unwrap();
+        for _ in 0..3 {
+            session.play_next_question().unwrap();
+            let note = session.game().unwrap().question().unwrap();
+            session.check_answer(&note.pitch).unwrap();
+        }
+        session.save_state().unwrap();
+
+        let mut other =
+            Session::<State, _>::with_backend(Config::default(), db, NullBackend);
+        assert!(other.load_game().unwrap());
+
+        // The uninterrupted game and the loaded one ask the same questions
+        for _ in 0..5 {
+            session.play_next_question().unwrap();
+            other.play_next_question().unwrap();
+            assert_eq!(
+                session.game().unwrap().question(),
+                other.game().unwrap().question()
+            );
+        }
+    }
+
+    // A game with the same seed asks the same questions,
+    // also after it's saved and loaded again
+    fn assert_replayed<G: Game>(settings: G::Settings)
+    where
+        G::Settings: Copy,
+        G::Question: PartialEq + fmt::Debug,
+    {
+        let db = Database::in_memory().unwrap();
+        let mut session = Session::<G, _>::with_backend(Config::default(), db.clone(), NullBackend);
+        session.new_game_with_seed(settings, 11).unwrap();
+
+        let mut fresh = Session::<G, _>::with_backend(
+            Config::default(),
+            Database::in_memory().unwrap(),
+            NullBackend,
+        );
+        fresh.new_game_with_seed(settings, 11).unwrap();
+        for _ in 0..2 {
+            session.play_next_question().unwrap();
+            fresh.play_next_question().unwrap();
+            assert_eq!(session.game().unwrap().question(), fresh.game().unwrap().question());
+        }
+        session.save_state().unwrap();
+
+        let mut other = Session::<G, _>::with_backend(Config::default(), db, NullBackend);
+        assert!(other.load_game().unwrap());
+        for _ in 0..2 {
+            session.play_next_question().unwrap();
+            other.play_next_question().unwrap();
+            assert!(session.game().unwrap().question().is_some());
+            assert_eq!(session.game().unwrap().question(), other.game().unwrap().question());
+        }
+    }
+
+    #[test]
+    fn replay_interval_and_chord_games() {
+        use games::{chords, intervals};
+
+        assert_replayed::<intervals::State>(());
+        assert_replayed::<chords::State>(());
+    }
+
+    #[test]
+    fn shared_seed() {
+        let mut config = Config::default();
+        config.seed = Some(7);
+
+        let questions = || {
+            let db = Database::in_memory().unwrap();
+            let mut session = Session::<State, _>::with_backend(config.clone(), db, NullBackend);
+            session.new_game(Tonality::major(Pitch::A)).unwrap();
+
+            (0..8)
+                .map(|_| {
+                    session.play_next_question().unwrap();
+                    session.game().unwrap().question().unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(questions(), questions());
+    }
+
+    #[test]
+    fn keep_game_when_log_fails() {
+        use diesel::prelude::*;
+        use diesel::sql_query;
+
+        let db = Database::in_memory().unwrap();
+        let mut session =
+            Session::<State, _>::with_backend(Config::default(), db.clone(), NullBackend);
+        session.new_game(Tonality::major(Pitch::C)).unwrap();
+        session.play_next_question().unwrap();
+        let note = session.game().unwrap().question().unwrap();
+
+        sql_query("DROP TABLE octave_answers")
+            .execute(db.connection())
+            .unwrap();
+        assert!(session.check_answer(&note.pitch).is_err());
+        assert_eq!(session.game().unwrap().exercise_score.right_count, 0);
+        assert_eq!(session.right_count(), 0);
+        // The attempt isn't used up
+        assert!(session.check_answer(&note.pitch).is_err());
+    }
+
+    #[test]
+    fn log_answers() {
+        use diesel::prelude::*;
+        use games::octaves::models::Answer;
+        use schema::octave_answers;
+
+        let db = Database::in_memory().unwrap();
+        let mut session =
+            Session::<State, _>::with_backend(Config::default(), db.clone(), NullBackend);
+        session.new_game(Tonality::major(Pitch::C)).unwrap();
+
+        session.play_next_question().unwrap();
+        session.repeat_question().unwrap();
+        session.repeat_question().unwrap();
+        let note = session.game().unwrap().question().unwrap();
+        let wrong = if note.pitch == Pitch::C { Pitch::D } else { Pitch::C };
+        session.check_answer(&wrong).unwrap();
+
+        session.play_next_question().unwrap();
+        let next_note = session.game().unwrap().question().unwrap();
+        session.check_answer(&next_note.pitch).unwrap();
+
+        let answers = octave_answers::table
+            .order(octave_answers::id)
+            .load::<Answer>(db.connection())
+            .unwrap();
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers[0].exercise, 1);
+        assert_eq!(answers[0].note, note.to_string());
+        assert_eq!(answers[0].answer, wrong.to_string());
+        assert!(!answers[0].correct);
+        assert_eq!(answers[0].repeats, 2);
+        assert!(answers[1].correct);
+        assert_eq!(answers[1].repeats, 0);
+    }
+}
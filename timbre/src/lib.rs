@@ -1,28 +1,38 @@
 extern crate chrono;
 #[macro_use]
 extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
 extern crate ears;
-extern crate failure;
 #[macro_use]
 extern crate lazy_static;
+extern crate lewton;
 extern crate rand;
 extern crate reqwest;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate sha2;
 extern crate tar;
+#[cfg(test)]
+extern crate tempdir;
 extern crate toml;
 extern crate xdg;
 
-pub fn establish_connection() -> diesel::SqliteConnection {
-    use diesel::prelude::*;
-    let database_url = xdg_dirs::DATA.join("timbre.db");
-    diesel::SqliteConnection::establish(database_url.to_str().unwrap())
-        .expect(&format!("Error connecting to {}", database_url.display()))
-}
+pub use database::Database;
+pub use error::Error;
 
+pub mod config;
+pub mod database;
+mod error;
 pub mod games;
+pub mod pack;
+pub mod player;
 pub mod sampler;
 pub mod schema;
+pub mod stats;
+pub mod synth;
+pub mod wav;
 
 pub mod xdg_dirs {
     use std::path::PathBuf;
@@ -40,5 +50,8 @@ pub mod xdg_dirs {
 
         pub static ref SAMPLES: PathBuf =
             BASE.create_data_directory("samples").unwrap();
+
+        pub static ref SYNTH: PathBuf =
+            BASE.create_cache_directory("synth").unwrap();
     }
 }
@@ -0,0 +1,401 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use rand::{thread_rng, Rng};
+use serde::de::{self, Deserialize, Deserializer};
+use toml;
+
+use config::Instrument;
+use error::Error;
+use games::octaves::note::{Note, Tonality};
+
+// Manifest at the root of a sample pack
+pub const MANIFEST: &'static str = "pack.toml";
+
+// Sample pack layout, e.g.
+//
+//     instrument = "Piano"
+//     version = 2
+//     format = "ogg"
+//     lowest = "Cb2"
+//     highest = "B#6"
+//     notes = "notes/{note}.{format}"
+//     cadences = "tonal-centers/IIVVIPAC - {tonality}.{format}"
+//
+// The note pattern may also use `{pitch}` and `{octave}`.
+// A pack without the manifest has the layout of `Pack::default()`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Pack {
+    pub instrument: String,
+    // Raised on every change of the samples
+    pub version: u32,
+    pub format: String,
+    #[serde(deserialize_with = "deserialize_note")]
+    pub lowest: Note,
+    #[serde(deserialize_with = "deserialize_note")]
+    pub highest: Note,
+    pub notes: String,
+    pub cadences: String,
+}
+
+impl Pack {
+    // Read the manifest of the pack in the directory
+    pub fn load(dir: &Path) -> Result<Pack, Error> {
+        let path = dir.join(MANIFEST);
+        if !path.exists() {
+            return Ok(Pack::default());
+        }
+
+        let mut serialized = String::new();
+        File::open(path)?.read_to_string(&mut serialized)?;
+        Pack::parse(&serialized)
+    }
+
+    pub fn parse(serialized: &str) -> Result<Pack, Error> {
+        let pack = toml::from_str::<Pack>(serialized)?;
+        if pack.lowest.midi() > pack.highest.midi() {
+            return Err(Error::InvalidPack(
+                "the lowest note is above the highest".to_owned(),
+            ));
+        }
+
+        Ok(pack)
+    }
+
+    // Directory name of the pack among the installed ones
+    pub fn id(&self) -> String {
+        self.instrument
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect()
+    }
+
+    pub fn contains(&self, note: Note) -> bool {
+        self.lowest.midi() <= note.midi() && note.midi() <= self.highest.midi()
+    }
+
+    // Path of the note sample relative to the pack,
+    // `None` if the note is out of the range
+    pub fn note_file(&self, note: Note) -> Option<String> {
+        if !self.contains(note) {
+            return None;
+        }
+
+        let file = self.notes
+            .replace("{note}", &note.to_string())
+            .replace("{pitch}", &note.pitch.to_string())
+            .replace("{octave}", &(note.octave as u8).to_string())
+            .replace("{format}", &self.format);
+        Some(file)
+    }
+
+    pub fn cadence_file(&self, tonality: Tonality) -> String {
+        self.cadences
+            .replace("{tonality}", &tonality.cadence().to_string())
+            .replace("{format}", &self.format)
+    }
+
+    // Files of every spelling of the note, any of them plays it
+    pub fn note_files(&self, note: Note) -> Vec<String> {
+        note.spellings()
+            .into_iter()
+            .filter_map(|n| self.note_file(n))
+            .collect()
+    }
+
+    // Files of the cadence from every spelling of the key
+    pub fn cadence_files(&self, tonality: Tonality) -> Vec<String> {
+        tonality
+            .cadence()
+            .spellings()
+            .into_iter()
+            .map(|t| self.cadence_file(t))
+            .collect()
+    }
+}
+
+impl Default for Pack {
+    fn default() -> Pack {
+        Pack {
+            instrument: "Piano".to_owned(),
+            version: 0,
+            format: "ogg".to_owned(),
+            lowest: "Cb2".parse().unwrap(),
+            highest: "B#6".parse().unwrap(),
+            notes: "notes/{note}.{format}".to_owned(),
+            cadences: "tonal-centers/IIVVIPAC - {tonality}.{format}".to_owned(),
+        }
+    }
+}
+
+// Pack installed in the samples directory
+#[derive(Clone, Debug)]
+pub struct Installed {
+    pub name: String,
+    pub path: PathBuf,
+    pub pack: Pack,
+}
+
+// Packs in the subdirectories of the samples directory sorted by name,
+// a pack synced without the manifest has at least the notes directory.
+// Samples synced before there were several packs lie right in the
+// directory and make the `default` pack.
+pub fn installed(root: &Path) -> Vec<Installed> {
+    let mut packs = Vec::new();
+
+    if root.join("notes").is_dir() {
+        packs.extend(load_installed("default", root));
+    }
+
+    let mut dirs = fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.join(MANIFEST).is_file() || path.join("notes").is_dir())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+
+    for dir in dirs.iter() {
+        let name = dir.file_name().unwrap().to_string_lossy().into_owned();
+        packs.extend(load_installed(&name, dir));
+    }
+
+    packs
+}
+
+fn load_installed(name: &str, path: &Path) -> Option<Installed> {
+    match Pack::load(path) {
+        Ok(pack) => Some(Installed {
+            name: name.to_owned(),
+            path: path.to_path_buf(),
+            pack,
+        }),
+        Err(e) => {
+            println!("Invalid sample pack {}: {}", name, e);
+            None
+        }
+    }
+}
+
+// Installed packs and the one playing now
+#[derive(Clone, Debug)]
+pub struct Instruments {
+    packs: Vec<Installed>,
+    instrument: Instrument,
+    current: usize,
+}
+
+impl Instruments {
+    pub fn new(packs: Vec<Installed>, instrument: Instrument) -> Instruments {
+        let mut instruments = Instruments {
+            packs,
+            instrument: Instrument::Default,
+            current: 0,
+        };
+
+        instruments.select(instrument);
+        instruments
+    }
+
+    pub fn select(&mut self, instrument: Instrument) {
+        self.current = match instrument {
+            Instrument::Pack(ref name) => {
+                let pos = self.packs.iter().position(|p| p.name == *name);
+                if pos.is_none() {
+                    println!("Sample pack {} is not installed", name);
+                }
+                pos.unwrap_or(0)
+            }
+            _ => 0,
+        };
+        self.instrument = instrument;
+
+        self.shuffle();
+    }
+
+    // Pick another pack in the random mode
+    pub fn shuffle(&mut self) {
+        if self.instrument == Instrument::Random && !self.packs.is_empty() {
+            self.current = thread_rng().gen_range(0, self.packs.len());
+        }
+    }
+
+    pub fn current(&self) -> Option<&Installed> {
+        self.packs.get(self.current)
+    }
+
+    // In the random mode a note missing in the current pack
+    // is taken from another one
+    pub fn note_path(&self, note: Note) -> Option<PathBuf> {
+        let file_path = |p: &Installed| existing(&p.path, p.pack.note_files(note));
+
+        match self.instrument {
+            Instrument::Random => self.current()
+                .and_then(&file_path)
+                .or_else(|| self.packs.iter().filter_map(&file_path).next()),
+            _ => self.current().and_then(file_path),
+        }
+    }
+
+    pub fn cadence_path(&self, tonality: Tonality) -> Option<PathBuf> {
+        self.current()
+            .and_then(|p| existing(&p.path, p.pack.cadence_files(tonality)))
+    }
+
+    pub fn contains(&self, note: Note) -> bool {
+        match self.instrument {
+            Instrument::Random => self.packs.iter().any(|p| p.pack.contains(note)),
+            _ => self.current().map_or(false, |p| p.pack.contains(note)),
+        }
+    }
+}
+
+// Path of the first file that exists in the pack, a pack may have only
+// one of the spellings. The first one if none of them exists.
+fn existing(dir: &Path, files: Vec<String>) -> Option<PathBuf> {
+    let paths = files.iter().map(|f| dir.join(f)).collect::<Vec<_>>();
+    paths
+        .iter()
+        .find(|path| path.is_file())
+        .or_else(|| paths.first())
+        .cloned()
+}
+
+fn deserialize_note<'de, D>(deserializer: D) -> Result<Note, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse::<Note>()
+        .map_err(|_| de::Error::custom(format!("invalid note {}", s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use games::octaves::note::{Mode, Pitch};
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    fn write_pack(dir: &Path, manifest: &str) {
+        fs::create_dir_all(dir).unwrap();
+        File::create(dir.join(MANIFEST))
+            .unwrap()
+            .write_all(manifest.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn default_pack_layout() {
+        let pack = Pack::default();
+        let note = "F#4".parse::<Note>().unwrap();
+
+        assert_eq!(pack.note_file(note), Some("notes/F#4.ogg".to_owned()));
+        assert_eq!(
+            pack.cadence_file(Tonality::new(Pitch::A, Mode::Dorian)),
+            "tonal-centers/IIVVIPAC - Amin.ogg"
+        );
+        assert_eq!(pack.note_file("D7".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn parse_pack() {
+        let pack = Pack::parse(
+            r#"
+            instrument = "Guitar"
+            format = "wav"
+            lowest = "E2"
+            highest = "E5"
+            notes = "{octave}/{pitch}.{format}"
+            "#,
+        ).unwrap();
+
+        assert_eq!(pack.instrument, "Guitar");
+        assert!(pack.contains("E2".parse().unwrap()));
+        assert!(pack.contains("Fb2".parse().unwrap()));
+        assert!(!pack.contains("Eb2".parse().unwrap()));
+        assert!(!pack.contains("F5".parse().unwrap()));
+        assert_eq!(
+            pack.note_file("Bb3".parse().unwrap()),
+            Some("3/Bb.wav".to_owned())
+        );
+        assert_eq!(
+            pack.cadence_file(Tonality::major(Pitch::G)),
+            "tonal-centers/IIVVIPAC - Gmaj.wav"
+        );
+
+        assert!(Pack::parse("lowest = \"H2\"").is_err());
+        assert!(Pack::parse("lowest = \"C5\"\nhighest = \"C4\"").is_err());
+    }
+
+    #[test]
+    fn installed_packs() {
+        let tmp = TempDir::new("samples").unwrap();
+        let root = tmp.path();
+
+        fs::create_dir_all(root.join("notes")).unwrap();
+        write_pack(&root.join("voice"), "instrument = \"Voice\"\nlowest = \"C3\"");
+        write_pack(&root.join("guitar"), "instrument = \"Guitar\"");
+        write_pack(&root.join("broken"), "lowest = 4");
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::create_dir_all(root.join("piano/notes")).unwrap();
+
+        let names = installed(root)
+            .into_iter()
+            .map(|p| p.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["default", "guitar", "piano", "voice"]);
+    }
+
+    #[test]
+    fn choose_instrument() {
+        let tmp = TempDir::new("samples").unwrap();
+        let root = tmp.path();
+        write_pack(&root.join("guitar"), "instrument = \"Guitar\"\nlowest = \"E2\"");
+        write_pack(&root.join("voice"), "instrument = \"Voice\"\nlowest = \"C3\"");
+
+        let low = "F2".parse::<Note>().unwrap();
+        let note = "A4".parse::<Note>().unwrap();
+
+        let voice = Instrument::Pack("voice".to_owned());
+        let mut instruments = Instruments::new(installed(root), voice);
+        assert_eq!(
+            instruments.note_path(note),
+            Some(root.join("voice/notes/A4.ogg"))
+        );
+        assert!(!instruments.contains(low));
+        assert_eq!(instruments.note_path(low), None);
+
+        // Only the flat is recorded
+        let sharp = "C#4".parse::<Note>().unwrap();
+        assert_eq!(
+            instruments.note_path(sharp),
+            Some(root.join("voice/notes/C#4.ogg"))
+        );
+        fs::create_dir_all(root.join("voice/notes")).unwrap();
+        File::create(root.join("voice/notes/Db4.ogg")).unwrap();
+        assert_eq!(
+            instruments.note_path(sharp),
+            Some(root.join("voice/notes/Db4.ogg"))
+        );
+
+        instruments.select(Instrument::Default);
+        assert_eq!(instruments.current().unwrap().name, "guitar");
+        assert!(instruments.contains(low));
+
+        instruments.select(Instrument::Random);
+        for _ in 0..10 {
+            instruments.shuffle();
+            assert!(instruments.contains(low));
+            assert_eq!(
+                instruments.note_path(low),
+                Some(root.join("guitar/notes/F2.ogg"))
+            );
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use ears::{AudioController, Sound};
+use lewton::inside_ogg::OggStreamReader;
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use config::{Config, Instrument, Sounds};
+use error::Error;
+use games::octaves::note::{Note, Tonality};
+use pack::{self, Instruments};
+use synth;
+use wav::{self, SAMPLE_RATE};
+use xdg_dirs;
+
+// What the games ask to play
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    // Notes sounding at the same time
+    Notes(Vec<Note>),
+    // I–IV–V–I establishing the tonality
+    Cadence(Tonality),
+}
+
+// Sound output, requests are played one after another
+pub trait AudioBackend {
+    fn play(&self, request: Request);
+
+    fn play_note(&self, note: Note) {
+        self.play(Request::Notes(vec![note]));
+    }
+
+    fn play_chord(&self, notes: &[Note]) {
+        self.play(Request::Notes(notes.to_vec()));
+    }
+
+    fn play_cadence(&self, tonality: Tonality) {
+        self.play(Request::Cadence(tonality));
+    }
+
+    fn can_play(&self, _note: Note) -> bool {
+        true
+    }
+
+    fn set_instrument(&mut self, _instrument: Instrument) {}
+
+    // Called before every new question
+    fn next_instrument(&mut self) {}
+}
+
+pub type Sample = String;
+
+// Plays the sample files with ears in a separate thread,
+// the samples sent together are played at the same time
+pub struct EarsBackend {
+    sounds: Sounds,
+    instruments: Instruments,
+    gramophone: mpsc::Sender<Vec<Sample>>,
+}
+
+impl EarsBackend {
+    pub fn new(sounds: Sounds, instrument: Instrument) -> EarsBackend {
+        let (tx, rx) = mpsc::channel::<Vec<Sample>>();
+
+        thread::spawn(move || {
+            for samples in rx.iter() {
+                let mut sounds = samples
+                    .iter()
+                    .filter_map(|path| {
+                        let sound = Sound::new(path);
+                        if sound.is_none() {
+                            println!("Failed to load {}", path);
+                        }
+                        sound
+                    })
+                    .collect::<Vec<_>>();
+
+                for snd in sounds.iter_mut() {
+                    snd.play();
+                }
+                while sounds.iter().any(|snd| snd.is_playing()) {
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+        });
+
+        let instruments = Instruments::new(pack::installed(&xdg_dirs::SAMPLES), instrument);
+
+        EarsBackend {
+            sounds,
+            instruments,
+            gramophone: tx,
+        }
+    }
+
+    fn note_sample(&self, note: Note) -> Option<Sample> {
+        match self.sounds {
+            Sounds::Samples => self.instruments
+                .note_path(note)
+                .map(|path| path.display().to_string()),
+            Sounds::Synth => synth::note_sample(note)
+                .map(|path| path.display().to_string())
+                .map_err(|e| println!("Failed to synthesize {}: {}", note, e))
+                .ok(),
+        }
+    }
+
+    // The first sample archive has only the major cadences,
+    // the ones missing in the pack are synthesized
+    fn cadence_sample(&self, tonality: Tonality) -> Option<Sample> {
+        let recorded = match self.sounds {
+            Sounds::Samples => self.instruments
+                .cadence_path(tonality)
+                .filter(|path| path.is_file()),
+            Sounds::Synth => None,
+        };
+
+        recorded
+            .map(Ok)
+            .unwrap_or_else(|| synth::cadence_sample(tonality))
+            .map(|path| path.display().to_string())
+            .map_err(|e| println!("Failed to synthesize {}: {}", tonality, e))
+            .ok()
+    }
+}
+
+impl AudioBackend for EarsBackend {
+    fn play(&self, request: Request) {
+        let samples = match request {
+            Request::Notes(notes) => notes
+                .iter()
+                .filter_map(|n| self.note_sample(*n))
+                .collect::<Vec<_>>(),
+            Request::Cadence(tonality) => self.cadence_sample(tonality).into_iter().collect(),
+        };
+
+        println!("{}\n", samples.join(", "));
+        if self.gramophone.send(samples).is_err() {
+            println!("The playback thread has stopped");
+        }
+    }
+
+    fn can_play(&self, note: Note) -> bool {
+        match self.sounds {
+            Sounds::Samples => self.instruments.contains(note),
+            Sounds::Synth => true,
+        }
+    }
+
+    fn set_instrument(&mut self, instrument: Instrument) {
+        self.instruments.select(instrument);
+    }
+
+    fn next_instrument(&mut self) {
+        self.instruments.shuffle();
+    }
+}
+
+// Plays nothing
+#[derive(Clone, Debug, Default)]
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn play(&self, _request: Request) {}
+}
+
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub request: Request,
+    pub at: Instant,
+}
+
+// Keeps every request instead of playing it, the clones share
+// the log so a test can hold one and pass another to the game
+#[derive(Clone, Debug, Default)]
+pub struct RecordingBackend {
+    log: Rc<RefCell<Vec<Record>>>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> RecordingBackend {
+        RecordingBackend::default()
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        self.log.borrow().clone()
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.log.borrow().iter().map(|r| r.request.clone()).collect()
+    }
+
+    pub fn clear(&self) {
+        self.log.borrow_mut().clear();
+    }
+}
+
+impl AudioBackend for RecordingBackend {
+    fn play(&self, request: Request) {
+        self.log.borrow_mut().push(Record {
+            request,
+            at: Instant::now(),
+        });
+    }
+}
+
+// Renders the requests one after another into a single track,
+// each one is followed by a gap of silence
+#[derive(Debug)]
+pub struct RenderingBackend {
+    // Seconds
+    gap: f32,
+    sounds: Sounds,
+    instruments: Instruments,
+    track: RefCell<Vec<f32>>,
+}
+
+impl RenderingBackend {
+    // Synthesizes every sound
+    pub fn new(gap: f32) -> RenderingBackend {
+        let instruments = Instruments::new(vec![], Instrument::Default);
+        RenderingBackend::with_instruments(gap, Sounds::Synth, instruments)
+    }
+
+    // Takes the sounds from the same place as the playback,
+    // the instrument is the one chosen for the game
+    pub fn with_config(gap: f32, config: &Config, game: &str) -> RenderingBackend {
+        let instruments = Instruments::new(
+            pack::installed(&xdg_dirs::SAMPLES),
+            config.instrument_for(game),
+        );
+        RenderingBackend::with_instruments(gap, config.sounds, instruments)
+    }
+
+    pub fn with_instruments(
+        gap: f32,
+        sounds: Sounds,
+        instruments: Instruments,
+    ) -> RenderingBackend {
+        RenderingBackend {
+            gap,
+            sounds,
+            instruments,
+            track: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn samples(&self) -> Vec<f32> {
+        self.track.borrow().clone()
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        wav::write(out, &self.track.borrow())
+    }
+
+    // Mix of the sample files, `None` if one of them is missing
+    fn recorded(&self, request: &Request) -> Option<Vec<f32>> {
+        let paths = match *request {
+            Request::Notes(ref notes) => notes
+                .iter()
+                .map(|&n| self.instruments.note_path(n))
+                .collect::<Option<Vec<_>>>()?,
+            Request::Cadence(tonality) => vec![self.instruments.cadence_path(tonality)?],
+        };
+
+        let mut mix = Vec::new();
+        for path in paths.iter() {
+            let samples = decode(path).map_err(|e| println!("{}", e)).ok()?;
+            if mix.len() < samples.len() {
+                mix.resize(samples.len(), 0.0);
+            }
+            for (m, s) in mix.iter_mut().zip(samples) {
+                *m += s / paths.len() as f32;
+            }
+        }
+        Some(mix)
+    }
+}
+
+impl AudioBackend for RenderingBackend {
+    // A sample that can't be read is synthesized instead
+    fn play(&self, request: Request) {
+        let recorded = match self.sounds {
+            Sounds::Samples => self.recorded(&request),
+            Sounds::Synth => None,
+        };
+        let samples = recorded.unwrap_or_else(|| match request {
+            Request::Notes(notes) => synth::render_chord(&notes),
+            Request::Cadence(tonality) => synth::render_cadence(tonality),
+        });
+        let silence = (self.gap * SAMPLE_RATE as f32) as usize;
+
+        let mut track = self.track.borrow_mut();
+        track.extend(samples);
+        track.extend(vec![0.0; silence]);
+    }
+
+    fn set_instrument(&mut self, instrument: Instrument) {
+        self.instruments.select(instrument);
+    }
+
+    fn next_instrument(&mut self) {
+        self.instruments.shuffle();
+    }
+}
+
+// Mono samples of the Ogg file at the rate of the track
+fn decode(path: &Path) -> Result<Vec<f32>, Error> {
+    let invalid = |e| Error::Decode(format!("{}: {}", path.display(), e));
+    let mut reader = OggStreamReader::new(File::open(path)?).map_err(&invalid)?;
+    let channels = reader.ident_hdr.audio_channels as usize;
+    let rate = reader.ident_hdr.audio_sample_rate;
+
+    let mut samples = Vec::new();
+    while let Some(packet) = reader.read_dec_packet_itl().map_err(&invalid)? {
+        for frame in packet.chunks(channels) {
+            let sum = frame.iter().map(|&s| s as f32).sum::<f32>();
+            samples.push(sum / (channels as f32 * i16::max_value() as f32));
+        }
+    }
+
+    Ok(resample(&samples, rate, SAMPLE_RATE))
+}
+
+// Linear interpolation, the samples are recorded at 44.1 or 48 kHz
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let count = (samples.len() as u64 * to as u64 / from as u64) as usize;
+    (0..count)
+        .map(|i| {
+            let pos = i as f64 * from as f64 / to as f64;
+            let index = pos as usize;
+            let frac = (pos - index as f64) as f32;
+            let next = samples.get(index + 1).cloned().unwrap_or(samples[index]);
+
+            samples[index] * (1.0 - frac) + next * frac
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use games::octaves::note::Pitch;
+
+    #[test]
+    fn recording_backend_logs_requests() {
+        let recorder = RecordingBackend::new();
+        let backend: &AudioBackend = &recorder.clone();
+        let note = "C4".parse::<Note>().unwrap();
+        let tonality = Tonality::major(Pitch::C);
+
+        backend.play_cadence(tonality);
+        backend.play_note(note);
+        backend.play_chord(&[note, note.transpose(2, 4).unwrap()]);
+
+        assert_eq!(
+            recorder.requests(),
+            vec![
+                Request::Cadence(tonality),
+                Request::Notes(vec![note]),
+                Request::Notes(vec![note, "E4".parse().unwrap()]),
+            ]
+        );
+
+        let records = recorder.records();
+        assert!(records[0].at <= records[2].at);
+
+        recorder.clear();
+        assert!(recorder.requests().is_empty());
+    }
+
+    #[test]
+    fn rendering_backend_appends_with_gaps() {
+        let renderer = RenderingBackend::new(0.5);
+        let note = "A4".parse::<Note>().unwrap();
+
+        renderer.play_note(note);
+        renderer.play_chord(&[note, note.transpose(2, 3).unwrap()]);
+
+        let note_len = synth::render_note(note).len();
+        let gap_len = SAMPLE_RATE as usize / 2;
+        let samples = renderer.samples();
+
+        assert_eq!(samples.len(), 2 * (note_len + gap_len));
+        assert!(samples[note_len..note_len + gap_len].iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn render_missing_samples_with_synth() {
+        let instruments = Instruments::new(vec![], Instrument::Default);
+        let renderer = RenderingBackend::with_instruments(0.0, Sounds::Samples, instruments);
+        let note = "A4".parse::<Note>().unwrap();
+
+        renderer.play_note(note);
+        assert_eq!(renderer.samples(), synth::render_note(note));
+    }
+
+    #[test]
+    fn resample_linearly() {
+        assert_eq!(resample(&[0.0, 1.0], 44_100, 44_100), vec![0.0, 1.0]);
+        assert_eq!(
+            resample(&[0.0, 1.0, 0.0, -1.0], 2, 4),
+            vec![0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -1.0]
+        );
+        assert_eq!(resample(&[0.0, 1.0, 0.0, -1.0], 4, 2), vec![0.0, 0.0]);
+    }
+}
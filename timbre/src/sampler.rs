@@ -1,38 +1,1100 @@
-use std::fs::{self, File};
-use std::io;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
 use tar::Archive;
-use failure::Error;
 use reqwest;
+use reqwest::header::{ByteRangeSpec, ContentLength, Range};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
 
+use config::Config;
+use error::Error;
+use games::octaves::note::{Mode, Note, Octave, TONALITIES};
+use pack::{self, Pack};
 use xdg_dirs;
 
-const FILE_URL: &'static str =
+// The first archive, it has no manifest
+pub const DEFAULT_SOURCE: &'static str =
     "https://dl.dropboxusercontent.com/s/mwgrnsgd8m718sn/Timbre.tar?dl=0";
 
-pub fn sync_files() -> Result<(), Error> {
-    fs::remove_dir_all(&*xdg_dirs::SAMPLES)?;
+// SHA-256 of every file in the archive in the `sha256sum` format,
+// required for the unpacked sources only
+const MANIFEST: &'static str = "SHA256SUMS";
 
-    download_archive()
-        .and_then(extract_archive)
-        .and_then(|path| {
-            fs::rename(path, &*xdg_dirs::SAMPLES)?;
+// Octaves the games play in
+const OCTAVES: [Octave; 5] = [
+    Octave::Great,
+    Octave::Small,
+    Octave::First,
+    Octave::Second,
+    Octave::Third,
+];
+
+// Where the samples are taken from
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    // HTTP(S) link to a tar archive
+    Url(String),
+    // HTTP(S) directory with the manifest and the unpacked samples,
+    // the link ends with a slash. Only the changed files are downloaded.
+    Unpacked(String),
+    // Tar archive or unpacked directory, either as a `file://` URL
+    // or a plain path
+    Local(PathBuf),
+}
+
+impl str::FromStr for Source {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Source, ()> {
+        if (s.starts_with("http://") || s.starts_with("https://")) && s.ends_with('/') {
+            Ok(Source::Unpacked(s.to_owned()))
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(Source::Url(s.to_owned()))
+        } else if s.starts_with("file://") {
+            Ok(Source::Local(PathBuf::from(&s["file://".len()..])))
+        } else if s.is_empty() || s.contains("://") {
+            Err(())
+        } else {
+            Ok(Source::Local(PathBuf::from(s)))
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Source::Url(ref url) | Source::Unpacked(ref url) => write!(f, "{}", url),
+            Source::Local(ref path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// What a running sync is doing
+#[derive(Clone, Debug, PartialEq)]
+pub enum Progress {
+    // Trying the source
+    Fetching(String),
+    // Bytes of the archive received so far, out of the total if known
+    Downloaded(u64, Option<u64>),
+    // Files unpacked or copied so far
+    Extracted(usize),
+    Verifying,
+    Installing,
+}
+
+// Sync running on a worker thread
+pub struct SyncHandle {
+    progress: mpsc::Receiver<Progress>,
+    result: mpsc::Receiver<Result<(), Error>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SyncHandle {
+    // Progress reported since the last call
+    pub fn progress(&self) -> Vec<Progress> {
+        self.progress.try_iter().collect()
+    }
+
+    // The sync stops at the next step and keeps the current samples
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    // `None` while the sync is running
+    pub fn result(&self) -> Option<Result<(), Error>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(stopped())),
+        }
+    }
+
+    // Block until the sync is over
+    pub fn wait(self) -> Result<(), Error> {
+        self.result
+            .recv()
+            .unwrap_or_else(|_| Err(stopped()))
+    }
+}
+
+// The worker thread has panicked
+fn stopped() -> Error {
+    Error::Sync("Synchronization stopped unexpectedly".to_owned())
+}
+
+// Reports the progress to the handle and stops the sync once it's cancelled
+struct Monitor {
+    progress: mpsc::Sender<Progress>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Monitor {
+    fn report(&self, progress: Progress) -> Result<(), Error> {
+        self.check()?;
+        // Nobody may be listening anymore, the sync goes on anyway
+        let _ = self.progress.send(progress);
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            Err(Error::SyncCancelled)
+        } else {
             Ok(())
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+// Download the samples next to the current ones and replace them
+// only when the new ones are verified, so a failed or cancelled sync
+// keeps the samples the user already has. The sources are tried in order
+// until one of them succeeds. The pack goes to the directory named
+// after its instrument, next to the packs of other instruments.
+pub fn sync_files(config: &Config) -> Result<SyncHandle, Error> {
+    let sources = config
+        .sources
+        .iter()
+        .map(|s| {
+            s.parse::<Source>()
+                .map_err(|_| Error::Sync(format!("Unknown sample source: {}", s)))
         })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(spawn_sync(sources, xdg_dirs::SAMPLES.clone(), xdg_dirs::DATA.clone()))
 }
 
-fn download_archive() -> Result<PathBuf, Error> {
-    let mut res = reqwest::get(FILE_URL)?;
-    let out_path = PathBuf::from(xdg_dirs::DATA.join("Timbre.tar"));
-    let mut out_file = File::create(&out_path)?;
-    io::copy(&mut res, &mut out_file)?;
+fn spawn_sync(sources: Vec<Source>, target: PathBuf, work_dir: PathBuf) -> SyncHandle {
+    let (progress_tx, progress_rx) = mpsc::channel();
+    let (result_tx, result_rx) = mpsc::channel();
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    let monitor = Monitor {
+        progress: progress_tx,
+        cancelled: cancelled.clone(),
+    };
+    thread::spawn(move || {
+        let result = sync_from(&sources, &target, &work_dir, &monitor);
+        let _ = result_tx.send(result);
+    });
 
-    Ok(out_path)
+    SyncHandle {
+        progress: progress_rx,
+        result: result_rx,
+        cancelled,
+    }
 }
 
-fn extract_archive(file_path: PathBuf) -> Result<PathBuf, Error> {
-    let file = File::open(&file_path)?;
+fn sync_from(
+    sources: &[Source],
+    target: &Path,
+    work_dir: &Path,
+    monitor: &Monitor,
+) -> Result<(), Error> {
+    if sources.is_empty() {
+        return Err(Error::Sync("No sample sources configured".to_owned()));
+    }
+
+    let staging = work_dir.join("samples.staging");
+    // Interrupted downloads are kept here to be resumed
+    let downloads = work_dir.join("samples.download");
+    let mut errors = Vec::new();
+
+    for source in sources.iter() {
+        remove_dir_if_exists(&staging)?;
+        fs::create_dir_all(&staging)?;
+
+        let result = monitor
+            .report(Progress::Fetching(source.to_string()))
+            .and_then(|_| fetch(source, &staging, target, &downloads, monitor))
+            .and_then(|dir| {
+                monitor.report(Progress::Verifying)?;
+                // Without the manifest the files can only be checked to be there
+                let verified = if dir.join(MANIFEST).exists() {
+                    verify_manifest(&dir)?
+                } else {
+                    list_files(&dir, Path::new(""))?
+                };
+                let pack = check_samples(&dir, &verified)?;
+
+                // Packs of different instruments are kept side by side
+                let pack_dir = target.join(pack.id());
+                let installed = Pack::load(&pack_dir)?;
+                if installed.version > pack.version {
+                    return Err(Error::Sync(format!(
+                        "Version {} of the {} pack is older than the installed {}",
+                        pack.version, pack.instrument, installed.version
+                    )));
+                }
+
+                monitor.report(Progress::Installing)?;
+                fs::create_dir_all(target)?;
+                swap_dirs(&dir, &pack_dir)
+            });
+        let _ = fs::remove_dir_all(&staging);
+
+        match result {
+            Ok(_) => {
+                let _ = fs::remove_dir_all(&downloads);
+                return Ok(());
+            }
+            // The other sources aren't tried after a cancel
+            Err(e) => if monitor.is_cancelled() {
+                return Err(e);
+            } else {
+                errors.push(format!("{}: {}", source, e))
+            },
+        }
+    }
+
+    Err(Error::Sync(errors.join("\n")))
+}
+
+// Put the samples from the source into the staging directory,
+// returns the directory with the manifest
+fn fetch(
+    source: &Source,
+    staging: &Path,
+    target: &Path,
+    downloads: &Path,
+    monitor: &Monitor,
+) -> Result<PathBuf, Error> {
+    match *source {
+        Source::Url(ref url) => {
+            fs::create_dir_all(downloads)?;
+            let path = downloads.join(format!("{}.tar", &sha256(url.as_bytes())[..16]));
+            download(url, &path, monitor)?;
+
+            let dir = extract_archive(&path, staging, monitor);
+            let _ = fs::remove_file(&path);
+            dir
+        }
+        Source::Unpacked(ref url) => fetch_changed(url, staging, target, downloads, monitor),
+        Source::Local(ref path) if path.is_dir() => {
+            let dir = staging.join("Timbre");
+            copy_dir(path, &dir, &mut 0, monitor)?;
+            Ok(dir)
+        }
+        Source::Local(ref path) => extract_archive(path, staging, monitor),
+    }
+}
+
+// Download the files whose hashes in the remote manifest differ from
+// the installed ones and copy the rest from the installed pack
+fn fetch_changed(
+    url: &str,
+    staging: &Path,
+    target: &Path,
+    downloads: &Path,
+    monitor: &Monitor,
+) -> Result<PathBuf, Error> {
+    let dir = staging.join("Timbre");
+    fs::create_dir_all(&dir)?;
+    fs::create_dir_all(downloads)?;
+
+    download(&file_url(url, MANIFEST), &dir.join(MANIFEST), monitor)?;
+    let remote = read_manifest(&dir)?;
+
+    // The pack manifest tells where the pack is installed,
+    // it's small enough to be downloaded every time
+    if let Some(hash) = remote.get(pack::MANIFEST) {
+        let dest = dir.join(pack::MANIFEST);
+        download_verified(&file_url(url, pack::MANIFEST), hash, &dest, downloads, monitor)?;
+    }
+    let pack_dir = target.join(Pack::load(&dir)?.id());
+    let local = read_manifest(&pack_dir).unwrap_or_default();
+
+    for (count, (name, hash)) in remote.iter().enumerate() {
+        let dest = dir.join(name);
+        if !dest.exists() {
+            fs::create_dir_all(dest.parent().unwrap())?;
+
+            let installed = pack_dir.join(name);
+            if local.get(name) == Some(hash) && installed.is_file() {
+                fs::copy(&installed, &dest)?;
+            } else {
+                download_verified(&file_url(url, name), hash, &dest, downloads, monitor)?;
+            }
+        }
+
+        monitor.report(Progress::Extracted(count + 1))?;
+    }
+
+    Ok(dir)
+}
+
+// Download the file and move it to the path if its hash matches.
+// The download is named by the hash, so an interrupted one is resumed
+// only for the same file.
+fn download_verified(
+    url: &str,
+    hash: &str,
+    path: &Path,
+    downloads: &Path,
+    monitor: &Monitor,
+) -> Result<(), Error> {
+    let download_path = downloads.join(hash);
+    download(url, &download_path, monitor)?;
+
+    if file_sha256(&download_path)? != hash {
+        fs::remove_file(&download_path)?;
+        return Err(Error::Sync(format!("Checksum mismatch for {}", url)));
+    }
+    fs::rename(&download_path, path)?;
+    Ok(())
+}
+
+// Download the file to the path. The data is written to a `.part` file
+// first and an interrupted download is resumed with a range request.
+fn download(url: &str, path: &Path, monitor: &Monitor) -> Result<(), Error> {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let offset = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+
+    let client = reqwest::Client::new();
+    let mut req = client.get(url);
+    if offset > 0 {
+        req.header(Range::Bytes(vec![ByteRangeSpec::AllFrom(offset)]));
+    }
+    let mut res = req.send()?;
+
+    let (mut out_file, mut downloaded) = match res.status() {
+        StatusCode::PartialContent => (OpenOptions::new().append(true).open(&part)?, offset),
+        // The server sends the whole file if it doesn't support ranges
+        status if status.is_success() => (File::create(&part)?, 0),
+        // The part is as long as the file or longer, it's stale
+        StatusCode::RangeNotSatisfiable if offset > 0 => {
+            fs::remove_file(&part)?;
+            return download(url, path, monitor);
+        }
+        status => return Err(Error::Sync(format!("Failed to download {}: {}", url, status))),
+    };
+    let total = res.headers()
+        .get::<ContentLength>()
+        .map(|len| downloaded + **len);
+
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let len = res.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+
+        out_file.write_all(&buf[..len])?;
+        downloaded += len as u64;
+        monitor.report(Progress::Downloaded(downloaded, total))?;
+    }
+
+    if total.map_or(false, |total| downloaded < total) {
+        return Err(Error::Sync(format!("Download of {} was interrupted", url)));
+    }
+    fs::rename(&part, path)?;
+    Ok(())
+}
+
+// Link to the file in the remote directory, the path parts are percent-encoded
+fn file_url(base: &str, name: &str) -> String {
+    let mut url = base.to_owned();
+
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url += &format!("%{:02X}", byte);
+        }
+    }
+
+    url
+}
+
+// The samples are either at the root of the archive
+// or in the `Timbre` directory
+fn extract_archive(file_path: &Path, dest: &Path, monitor: &Monitor) -> Result<PathBuf, Error> {
+    let file = File::open(file_path)?;
     let mut a = Archive::new(file);
-    a.unpack(&*xdg_dirs::DATA)?;
-    Ok(xdg_dirs::DATA.join("Timbre"))
+
+    for (count, entry) in a.entries()?.enumerate() {
+        entry?.unpack_in(dest)?;
+        monitor.report(Progress::Extracted(count + 1))?;
+    }
+
+    if dest.join("Timbre").is_dir() {
+        Ok(dest.join("Timbre"))
+    } else {
+        Ok(dest.to_path_buf())
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path, count: &mut usize, monitor: &Monitor) -> Result<(), Error> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest, count, monitor)?;
+        } else {
+            fs::copy(entry.path(), dest)?;
+            *count += 1;
+            monitor.report(Progress::Extracted(*count))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Sounds of the pack the games may ask for, each one with the files
+// of its spellings relative to the pack. The player takes any of them,
+// so a sound is there if one of its files is.
+//
+// The notes of every scale in the octaves of the games are required
+// and the cadences of the major keys. The player synthesizes the minor
+// cadences a pack lacks, the first archive has none of them.
+pub fn required_samples(pack: &Pack) -> Vec<Vec<String>> {
+    let mut notes = BTreeMap::new();
+    for tonality in TONALITIES.iter() {
+        for &pitch in tonality.gamut().scale.iter() {
+            for &octave in OCTAVES.iter() {
+                let note = Note { octave, pitch };
+                notes.entry(note.midi()).or_insert(note);
+            }
+        }
+    }
+
+    let mut cadences = BTreeMap::new();
+    for tonality in Mode::Major.tonalities() {
+        cadences.entry(tonality.key.pitch_class()).or_insert(tonality);
+    }
+
+    let mut samples = notes
+        .values()
+        .map(|&note| pack.note_files(note))
+        .filter(|files| !files.is_empty())
+        .collect::<Vec<_>>();
+    samples.extend(cadences.values().map(|&t| pack.cadence_files(t)));
+
+    samples
+}
+
+// Paths of the files in the directory relative to the prefix
+fn list_files(dir: &Path, prefix: &Path) -> Result<BTreeSet<String>, Error> {
+    let mut files = BTreeSet::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = prefix.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            files.extend(list_files(&entry.path(), &name)?);
+        } else {
+            // The same separator as in the manifest
+            let parts = name.iter().map(|p| p.to_string_lossy()).collect::<Vec<_>>();
+            files.insert(parts.join("/"));
+        }
+    }
+
+    Ok(files)
+}
+
+// Names of the files listed in the manifest with their hashes
+pub fn read_manifest(dir: &Path) -> Result<BTreeMap<String, String>, Error> {
+    let manifest = File::open(dir.join(MANIFEST))
+        .map_err(|_| Error::Sync("The samples archive has no manifest".to_owned()))?;
+    let mut entries = BTreeMap::new();
+
+    for line in BufReader::new(manifest).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut parts = line.splitn(2, ' ');
+        let (hash, name) = match (parts.next(), parts.next()) {
+            // The name may be marked as binary with an asterisk
+            (Some(hash), Some(name)) => (hash, name.trim_matches(|c| c == ' ' || c == '*')),
+            _ => return Err(Error::Sync(format!("Invalid manifest line: {}", line))),
+        };
+
+        // Keep the names inside the samples directory
+        if Path::new(name).is_absolute() || name.split('/').any(|part| part == "..") {
+            return Err(Error::Sync(format!("Invalid sample path: {}", name)));
+        }
+
+        entries.insert(name.to_owned(), hash.to_lowercase());
+    }
+
+    Ok(entries)
+}
+
+// Check every file listed in the manifest against its hash,
+// returns the names of the verified files
+pub fn verify_manifest(dir: &Path) -> Result<BTreeSet<String>, Error> {
+    let mut verified = BTreeSet::new();
+
+    for (name, hash) in read_manifest(dir)? {
+        let actual = file_sha256(&dir.join(&name))
+            .map_err(|e| Error::Sync(format!("Can't read sample {}: {}", name, e)))?;
+        if actual != hash {
+            return Err(Error::Sync(format!("Checksum mismatch for {}", name)));
+        }
+
+        verified.insert(name);
+    }
+
+    Ok(verified)
+}
+
+fn check_samples(dir: &Path, verified: &BTreeSet<String>) -> Result<Pack, Error> {
+    let pack = Pack::load(dir)?;
+    let mut required = required_samples(&pack);
+    if dir.join(pack::MANIFEST).exists() {
+        required.push(vec![pack::MANIFEST.to_owned()]);
+    }
+
+    let missing = required
+        .into_iter()
+        .filter(|files| !files.iter().any(|f| verified.contains(f)))
+        .map(|files| files[0].clone())
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Ok(pack)
+    } else {
+        Err(Error::Sync(format!(
+            "{} samples are missing, e.g. {}",
+            missing.len(),
+            missing[0]
+        )))
+    }
+}
+
+fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    Ok(sha256(&bytes))
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::default();
+    hasher.input(bytes);
+    format!("{:x}", hasher.result())
+}
+
+// Put the staged directory in place of the target. A directory can't be
+// replaced by a single rename, so the old one is moved aside first and
+// moved back if the new one can't take its place.
+fn swap_dirs(staged: &Path, target: &Path) -> Result<(), Error> {
+    let backup = target.with_extension("old");
+    remove_dir_if_exists(&backup)?;
+
+    if target.exists() {
+        fs::rename(target, &backup)?;
+    }
+    if let Err(e) = fs::rename(staged, target) {
+        if backup.exists() {
+            fs::rename(&backup, target)?;
+        }
+        return Err(e.into());
+    }
+
+    let _ = fs::remove_dir_all(&backup);
+    Ok(())
+}
+
+fn remove_dir_if_exists(path: &Path) -> io::Result<()> {
+    if path.exists() {
+        fs::remove_dir_all(path)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tar::Builder;
+    use tempdir::TempDir;
+
+    fn write_file(path: &Path, content: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap().write_all(content).unwrap();
+    }
+
+    fn monitor() -> Monitor {
+        Monitor {
+            progress: mpsc::channel().0,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // One file of every required sample
+    fn sample_names(pack: &Pack) -> BTreeSet<String> {
+        required_samples(pack)
+            .into_iter()
+            .map(|files| files[0].clone())
+            .collect()
+    }
+
+    // Directory with every sample and the manifest
+    fn write_samples(dir: &Path) {
+        for name in sample_names(&Pack::default()).iter() {
+            write_file(&dir.join(name), name.as_bytes());
+        }
+
+        write_manifest(dir);
+    }
+
+    fn write_manifest(dir: &Path) {
+        let mut names = sample_names(&Pack::load(dir).unwrap());
+        if dir.join(pack::MANIFEST).exists() {
+            names.insert(pack::MANIFEST.to_owned());
+        }
+
+        let mut manifest = String::new();
+        for name in names.iter() {
+            let hash = file_sha256(&dir.join(name)).unwrap();
+            manifest += &format!("{}  {}\n", hash, name);
+        }
+
+        write_file(&dir.join(MANIFEST), manifest.as_bytes());
+    }
+
+    fn read_file(path: &Path) -> String {
+        let mut content = String::new();
+        File::open(path)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    // HTTP stand-in serving the files of the directory, understands
+    // `Range: bytes=N-`. Returns the base URL and the log of the requests.
+    fn serve(dir: &Path) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let dir = dir.to_path_buf();
+        let server_log = log.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let _ = respond(stream.unwrap(), &dir, &server_log);
+            }
+        });
+
+        (url, log)
+    }
+
+    fn respond(stream: TcpStream, dir: &Path, log: &Mutex<Vec<String>>) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+
+        let mut offset = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            let header = header.trim().to_lowercase();
+            if header.is_empty() {
+                break;
+            }
+            if header.starts_with("range: bytes=") {
+                offset = header["range: bytes=".len()..]
+                    .trim_matches('-')
+                    .parse()
+                    .unwrap();
+            }
+        }
+
+        let path = request.split(' ').nth(1).unwrap().trim_matches('/');
+        let name = percent_decode(path);
+        log.lock().unwrap().push(if offset > 0 {
+            format!("{} from {}", name, offset)
+        } else {
+            name.clone()
+        });
+
+        let mut out = stream;
+        let mut body = Vec::new();
+        if File::open(dir.join(&name))
+            .and_then(|mut file| file.read_to_end(&mut body))
+            .is_err()
+        {
+            return write!(out, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+        }
+
+        if offset >= body.len() && offset > 0 {
+            return write!(
+                out,
+                "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\n\r\n"
+            );
+        } else if offset > 0 {
+            write!(
+                out,
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n",
+                offset,
+                body.len() - 1,
+                body.len()
+            )?;
+        } else {
+            write!(out, "HTTP/1.1 200 OK\r\n")?;
+        }
+        write!(out, "Content-Length: {}\r\n\r\n", body.len() - offset)?;
+        out.write_all(&body[offset..])
+    }
+
+    fn percent_decode(s: &str) -> String {
+        let mut bytes = Vec::new();
+        let mut i = 0;
+        while i < s.len() {
+            if &s[i..i + 1] == "%" {
+                bytes.push(u8::from_str_radix(&s[i + 1..i + 3], 16).unwrap());
+                i += 3;
+            } else {
+                bytes.push(s.as_bytes()[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn verify_sample_manifest() {
+        let tmp = TempDir::new("samples").unwrap();
+        let dir = tmp.path();
+
+        write_file(&dir.join("notes/C4.ogg"), b"abc");
+        write_file(
+            &dir.join(MANIFEST),
+            b"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  notes/C4.ogg\n",
+        );
+        let verified = verify_manifest(dir).unwrap();
+        assert!(verified.contains("notes/C4.ogg"));
+
+        write_file(&dir.join("notes/C4.ogg"), b"abd");
+        assert!(verify_manifest(dir).is_err());
+
+        fs::remove_file(dir.join("notes/C4.ogg")).unwrap();
+        assert!(verify_manifest(dir).is_err());
+
+        write_file(&dir.join(MANIFEST), b"ba7816bf  ../C4.ogg\n");
+        assert!(verify_manifest(dir).is_err());
+    }
+
+    #[test]
+    fn required_samples_cover_games() {
+        let tmp = TempDir::new("samples").unwrap();
+        let required = required_samples(&Pack::default());
+        let requires = |name: &str| {
+            required
+                .iter()
+                .filter(|files| files.contains(&name.to_owned()))
+                .count()
+        };
+
+        assert_eq!(requires("notes/C2.ogg"), 1);
+        assert_eq!(requires("notes/B#6.ogg"), 1);
+        assert_eq!(requires("tonal-centers/IIVVIPAC - Cmaj.ogg"), 1);
+        assert_eq!(requires("tonal-centers/IIVVIPAC - Ebmin.ogg"), 0);
+        // Any spelling of the sound will do
+        assert!(required.contains(&vec![
+            "notes/C#4.ogg".to_owned(),
+            "notes/Db4.ogg".to_owned(),
+        ]));
+        assert!(required.contains(&vec![
+            "tonal-centers/IIVVIPAC - F#maj.ogg".to_owned(),
+            "tonal-centers/IIVVIPAC - Gbmaj.ogg".to_owned(),
+        ]));
+        // Every sound from Cb2 to B#6 and a cadence for each key
+        assert_eq!(required.len(), 62 + 12);
+
+        let mut samples = sample_names(&Pack::default());
+        assert!(check_samples(tmp.path(), &BTreeSet::new()).is_err());
+        assert!(check_samples(tmp.path(), &samples).is_ok());
+        samples.remove("notes/C#4.ogg");
+        assert!(check_samples(tmp.path(), &samples).is_err());
+        samples.insert("notes/Db4.ogg".to_owned());
+        assert!(check_samples(tmp.path(), &samples).is_ok());
+
+        // Only the notes in the range of the pack are needed
+        write_file(
+            &tmp.path().join(pack::MANIFEST),
+            b"lowest = \"C4\"\nhighest = \"B4\"\n",
+        );
+        assert!(check_samples(tmp.path(), &samples).is_err());
+
+        let pack = Pack::load(tmp.path()).unwrap();
+        let mut samples = sample_names(&pack);
+        assert!(!samples.contains("notes/C2.ogg"));
+        samples.insert(pack::MANIFEST.to_owned());
+        assert!(check_samples(tmp.path(), &samples).is_ok());
+    }
+
+    #[test]
+    fn swap_dirs_replaces_target() {
+        let tmp = TempDir::new("samples").unwrap();
+        let staged = tmp.path().join("staged");
+        let target = tmp.path().join("samples");
+
+        write_file(&staged.join("new"), b"");
+        write_file(&target.join("old"), b"");
+        swap_dirs(&staged, &target).unwrap();
+
+        assert!(target.join("new").exists());
+        assert!(!target.join("old").exists());
+        assert!(!staged.exists());
+        assert!(!target.with_extension("old").exists());
+
+        // Nothing to swap in, the current samples stay
+        assert!(swap_dirs(&staged, &target).is_err());
+        assert!(target.join("new").exists());
+    }
+
+    #[test]
+    fn parse_source_from_str() {
+        assert_eq!(
+            "https://example.com/Timbre.tar".parse::<Source>(),
+            Ok(Source::Url("https://example.com/Timbre.tar".to_owned()))
+        );
+        assert_eq!(
+            "file:///mnt/share/Timbre.tar".parse::<Source>(),
+            Ok(Source::Local(PathBuf::from("/mnt/share/Timbre.tar")))
+        );
+        assert_eq!(
+            "/mnt/share/samples".parse::<Source>(),
+            Ok(Source::Local(PathBuf::from("/mnt/share/samples")))
+        );
+        assert_eq!(
+            "https://example.com/samples/".parse::<Source>(),
+            Ok(Source::Unpacked("https://example.com/samples/".to_owned()))
+        );
+        assert!("ftp://example.com/Timbre.tar".parse::<Source>().is_err());
+        assert!("".parse::<Source>().is_err());
+    }
+
+    #[test]
+    fn sync_from_directory() {
+        let tmp = TempDir::new("samples").unwrap();
+        let source = tmp.path().join("share");
+        let target = tmp.path().join("samples");
+        write_samples(&source);
+
+        let sources = vec![Source::Local(source.clone())];
+        sync_from(&sources, &target, tmp.path(), &monitor()).unwrap();
+
+        assert!(target.join("piano/notes/C4.ogg").exists());
+        let packs = pack::installed(&target);
+        assert_eq!(packs.len(), 1);
+        assert_eq!(packs[0].name, "piano");
+        assert_eq!(packs[0].pack, Pack::default());
+        // The source is copied, not moved
+        assert!(source.join("notes/C4.ogg").exists());
+        assert!(!tmp.path().join("samples.staging").exists());
+    }
+
+    #[test]
+    fn sync_from_archive_without_manifest() {
+        let tmp = TempDir::new("samples").unwrap();
+        let dir = tmp.path().join("share");
+        let archive = tmp.path().join("Timbre.tar");
+        let target = tmp.path().join("samples");
+        write_samples(&dir);
+        fs::remove_file(dir.join(MANIFEST)).unwrap();
+
+        let mut builder = Builder::new(File::create(&archive).unwrap());
+        builder.append_dir_all("Timbre", &dir).unwrap();
+        builder.finish().unwrap();
+
+        let sources = vec![Source::Local(archive.clone())];
+        sync_from(&sources, &target, tmp.path(), &monitor()).unwrap();
+        assert!(target.join("piano/notes/C4.ogg").exists());
+
+        // The missing samples are still found
+        fs::remove_file(dir.join("notes/C4.ogg")).unwrap();
+        let mut builder = Builder::new(File::create(&archive).unwrap());
+        builder.append_dir_all("Timbre", &dir).unwrap();
+        builder.finish().unwrap();
+        assert!(sync_from(&sources, &target, tmp.path(), &monitor()).is_err());
+    }
+
+    #[test]
+    fn sync_from_archive_after_failed_source() {
+        let tmp = TempDir::new("samples").unwrap();
+        let dir = tmp.path().join("share");
+        let archive = tmp.path().join("Timbre.tar");
+        let target = tmp.path().join("samples");
+        write_samples(&dir);
+
+        let mut builder = Builder::new(File::create(&archive).unwrap());
+        builder.append_dir_all("Timbre", &dir).unwrap();
+        builder.finish().unwrap();
+
+        let sources = vec![
+            Source::Local(tmp.path().join("missing")),
+            format!("file://{}", archive.display()).parse().unwrap(),
+        ];
+        sync_from(&sources, &target, tmp.path(), &monitor()).unwrap();
+
+        assert!(target.join("piano/notes/C4.ogg").exists());
+    }
+
+    #[test]
+    fn failed_sync_keeps_samples() {
+        let tmp = TempDir::new("samples").unwrap();
+        let source = tmp.path().join("share");
+        let target = tmp.path().join("samples");
+        write_samples(&source);
+        write_file(&target.join("piano/notes/C4.ogg"), b"old");
+        fs::remove_file(source.join("notes/D4.ogg")).unwrap();
+
+        let sources = vec![Source::Local(source)];
+        assert!(sync_from(&sources, &target, tmp.path(), &monitor()).is_err());
+        assert!(sync_from(&[], &target, tmp.path(), &monitor()).is_err());
+
+        assert_eq!(read_file(&target.join("piano/notes/C4.ogg")), "old");
+    }
+
+    #[test]
+    fn sync_reports_progress() {
+        let tmp = TempDir::new("samples").unwrap();
+        let source = tmp.path().join("share");
+        let target = tmp.path().join("samples");
+        write_samples(&source);
+        let files = fs::read_dir(source.join("notes")).unwrap().count()
+            + fs::read_dir(source.join("tonal-centers")).unwrap().count() + 1;
+
+        let handle = spawn_sync(
+            vec![Source::Local(source.clone())],
+            target.clone(),
+            tmp.path().to_path_buf(),
+        );
+        let mut progress = Vec::new();
+        let result = loop {
+            progress.extend(handle.progress());
+            if let Some(result) = handle.result() {
+                break result;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        progress.extend(handle.progress());
+
+        result.unwrap();
+        assert_eq!(progress[0], Progress::Fetching(source.display().to_string()));
+        assert_eq!(progress[files], Progress::Extracted(files));
+        assert_eq!(
+            &progress[files + 1..],
+            &[Progress::Verifying, Progress::Installing]
+        );
+    }
+
+    #[test]
+    fn cancelled_sync_keeps_samples() {
+        let tmp = TempDir::new("samples").unwrap();
+        let source = tmp.path().join("share");
+        let target = tmp.path().join("samples");
+        write_samples(&source);
+        write_file(&target.join("piano/notes/C4.ogg"), b"old");
+
+        let monitor = monitor();
+        monitor.cancelled.store(true, Ordering::SeqCst);
+        let sources = vec![Source::Local(source.clone()), Source::Local(source)];
+        match sync_from(&sources, &target, tmp.path(), &monitor) {
+            Err(Error::SyncCancelled) => (),
+            res => panic!("Expected a cancel, got {:?}", res),
+        }
+        assert!(!tmp.path().join("samples.staging").exists());
+        assert_eq!(read_file(&target.join("piano/notes/C4.ogg")), "old");
+    }
+
+    #[test]
+    fn resume_interrupted_download() {
+        let tmp = TempDir::new("samples").unwrap();
+        let share = tmp.path().join("share");
+        let content = (0..1000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        write_file(&share.join("Timbre.tar"), &content);
+        let (url, log) = serve(&share);
+
+        let path = tmp.path().join("Timbre.tar");
+        let part = tmp.path().join("Timbre.tar.part");
+        write_file(&part, &content[..400]);
+        download(&format!("{}Timbre.tar", url), &path, &monitor()).unwrap();
+
+        let mut downloaded = Vec::new();
+        File::open(&path)
+            .unwrap()
+            .read_to_end(&mut downloaded)
+            .unwrap();
+        assert_eq!(downloaded, content);
+        assert!(!part.exists());
+
+        // A part longer than the file is stale, the download starts over
+        write_file(&part, &[0; 2000]);
+        download(&format!("{}Timbre.tar", url), &path, &monitor()).unwrap();
+        assert_eq!(file_sha256(&path).unwrap(), sha256(&content));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["Timbre.tar from 400", "Timbre.tar from 2000", "Timbre.tar"]
+        );
+    }
+
+    #[test]
+    fn sync_only_changed_files() {
+        let tmp = TempDir::new("samples").unwrap();
+        let share = tmp.path().join("share");
+        let target = tmp.path().join("samples");
+        write_samples(&share);
+        let (url, log) = serve(&share);
+        let sources = vec![url.parse::<Source>().unwrap()];
+
+        sync_from(&sources, &target, tmp.path(), &monitor()).unwrap();
+        let samples = sample_names(&Pack::default()).len();
+        assert_eq!(log.lock().unwrap().len(), samples + 1);
+        assert_eq!(read_file(&target.join("piano/notes/C#4.ogg")), "notes/C#4.ogg");
+
+        write_file(&share.join("notes/C#4.ogg"), b"new");
+        write_manifest(&share);
+        log.lock().unwrap().clear();
+
+        sync_from(&sources, &target, tmp.path(), &monitor()).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec![MANIFEST, "notes/C#4.ogg"]);
+        assert_eq!(read_file(&target.join("piano/notes/C#4.ogg")), "new");
+        assert_eq!(read_file(&target.join("piano/notes/C4.ogg")), "notes/C4.ogg");
+        assert!(!tmp.path().join("samples.download").exists());
+    }
+
+    #[test]
+    fn older_pack_is_not_installed() {
+        let tmp = TempDir::new("samples").unwrap();
+        let source = tmp.path().join("share");
+        let target = tmp.path().join("samples");
+        let sources = vec![Source::Local(source.clone())];
+
+        write_samples(&source);
+        write_file(&source.join(pack::MANIFEST), b"version = 2");
+        write_manifest(&source);
+        sync_from(&sources, &target, tmp.path(), &monitor()).unwrap();
+
+        write_file(&source.join(pack::MANIFEST), b"version = 1");
+        write_manifest(&source);
+        assert!(sync_from(&sources, &target, tmp.path(), &monitor()).is_err());
+        assert_eq!(Pack::load(&target.join("piano")).unwrap().version, 2);
+    }
 }
@@ -1,3 +1,61 @@
+table! {
+    chord_game_states (id) {
+        id -> Integer,
+        exercise -> Integer,
+        chord -> Text,
+        chords -> Text,
+        right_count -> Integer,
+        total_count -> Integer,
+        game_id -> Integer,
+        draws -> BigInt,
+    }
+}
+
+table! {
+    chord_games (id) {
+        id -> Integer,
+        created_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+        seed -> BigInt,
+    }
+}
+
+table! {
+    interval_game_states (id) {
+        id -> Integer,
+        exercise -> Integer,
+        question -> Text,
+        questions -> Text,
+        right_count -> Integer,
+        total_count -> Integer,
+        game_id -> Integer,
+        draws -> BigInt,
+    }
+}
+
+table! {
+    interval_games (id) {
+        id -> Integer,
+        created_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+        seed -> BigInt,
+    }
+}
+
+table! {
+    octave_answers (id) {
+        id -> Integer,
+        game_id -> Integer,
+        exercise -> Integer,
+        note -> Text,
+        answer -> Text,
+        correct -> Bool,
+        response_ms -> Integer,
+        repeats -> Integer,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     octave_game_states (id) {
         id -> Integer,
@@ -8,6 +66,9 @@ table! {
         right_count -> Integer,
         total_count -> Integer,
         game_id -> Integer,
+        draws -> BigInt,
+        exercise_right_count -> Integer,
+        exercise_total_count -> Integer,
     }
 }
 
@@ -17,9 +78,31 @@ table! {
         tonality -> Text,
         created_at -> Timestamp,
         finished_at -> Nullable<Timestamp>,
+        seed -> BigInt,
+    }
+}
+
+table! {
+    octave_memory (tonality, note) {
+        tonality -> Text,
+        note -> Text,
+        level -> Integer,
+        updated_at -> Timestamp,
     }
 }
 
+joinable!(chord_game_states -> chord_games (game_id));
+joinable!(interval_game_states -> interval_games (game_id));
+joinable!(octave_answers -> octave_games (game_id));
 joinable!(octave_game_states -> octave_games (game_id));
 
-allow_tables_to_appear_in_same_query!(octave_game_states, octave_games,);
+allow_tables_to_appear_in_same_query!(
+    chord_game_states,
+    chord_games,
+    interval_game_states,
+    interval_games,
+    octave_answers,
+    octave_game_states,
+    octave_games,
+    octave_memory,
+);
@@ -0,0 +1,465 @@
+use chrono::{self, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use games::octaves::models::Answer;
+use games::octaves::note::{AlteredDegree, Note, Octave, Pitch, Tonality};
+use schema::{octave_answers, octave_games};
+
+// What the answers are grouped by
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Group {
+    // Degree of the note above the key of the game, e.g. b3,
+    // the same for every mode
+    Degree,
+    Pitch,
+    Octave,
+    Tonality,
+    Exercise,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Key {
+    Degree(AlteredDegree),
+    Pitch(Pitch),
+    Octave(Octave),
+    // Tonality as it is stored, e.g. "Cmaj"
+    Tonality(String),
+    Exercise(u8),
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Key::Degree(degree) => write!(f, "{}", degree),
+            Key::Pitch(pitch) => write!(f, "{}", pitch),
+            Key::Octave(octave) => write!(f, "{}", octave as u8),
+            Key::Tonality(ref tonality) => write!(f, "{}", tonality),
+            Key::Exercise(num) => write!(f, "ex. #{}", num),
+        }
+    }
+}
+
+// Dates of the answers to take into account,
+// an open bound is not checked
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Period {
+    pub since: Option<NaiveDateTime>,
+    // Exclusive
+    pub until: Option<NaiveDateTime>,
+}
+
+impl Period {
+    pub fn all() -> Period {
+        Period::default()
+    }
+
+    pub fn new(since: NaiveDateTime, until: NaiveDateTime) -> Period {
+        Period {
+            since: Some(since),
+            until: Some(until),
+        }
+    }
+
+    // Up to now from the same time the days before
+    pub fn last_days(days: i64) -> Period {
+        Period {
+            since: Some(Utc::now().naive_utc() - chrono::Duration::days(days)),
+            until: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accuracy {
+    pub key: Key,
+    pub right_count: u32,
+    pub total_count: u32,
+    // Average time to answer
+    pub response_time: Duration,
+}
+
+impl Accuracy {
+    // Share of the right answers from 0 to 1
+    pub fn ratio(&self) -> f32 {
+        if self.total_count == 0 {
+            return 0.0;
+        }
+
+        self.right_count as f32 / self.total_count as f32
+    }
+}
+
+// Accuracy of the octaves answers in the period by the group,
+// ordered by the key
+pub fn accuracy(
+    conn: &SqliteConnection,
+    group: Group,
+    period: Period,
+) -> QueryResult<Vec<Accuracy>> {
+    let mut totals = BTreeMap::new();
+
+    for (answer, tonality) in answers(conn, period, None)? {
+        let key = match key(group, &answer, &tonality) {
+            Some(key) => key,
+            None => continue,
+        };
+
+        let total = totals.entry(key).or_insert((0, 0, 0));
+        if answer.correct {
+            total.0 += 1;
+        }
+        total.1 += 1;
+        total.2 += answer.response_ms as u64;
+    }
+
+    let stats = totals
+        .into_iter()
+        .map(|(key, (right_count, total_count, response_ms))| Accuracy {
+            key,
+            right_count,
+            total_count,
+            response_time: Duration::from_millis(response_ms / total_count as u64),
+        })
+        .collect();
+
+    Ok(stats)
+}
+
+// How often each pitch was given for each played pitch
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Confusion {
+    // Counts by the played and the given pitches
+    counts: BTreeMap<(Pitch, Pitch), u32>,
+}
+
+impl Confusion {
+    pub fn count(&self, played: Pitch, given: Pitch) -> u32 {
+        self.counts.get(&(played, given)).cloned().unwrap_or(0)
+    }
+
+    // Times the pitch was played
+    pub fn total(&self, played: Pitch) -> u32 {
+        self.counts
+            .iter()
+            .filter(|&(&(p, _), _)| p == played)
+            .map(|(_, &count)| count)
+            .sum()
+    }
+
+    // Every played or given pitch in the order of the sound, the rows
+    // and the columns of the matrix
+    pub fn pitches(&self) -> Vec<Pitch> {
+        let mut pitches = self.counts
+            .keys()
+            .flat_map(|&(played, given)| vec![played, given])
+            .collect::<Vec<_>>();
+        pitches.sort_by_key(|&p| (p.pitch_class(), p));
+        pitches.dedup();
+        pitches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+// Confusion matrix of the octaves answers in the tonality and
+// the exercise. The pitches are spelled as in the tonality, the answers
+// are logged with sharps but a Db and a C# are the same row.
+pub fn confusion(
+    conn: &SqliteConnection,
+    tonality: Tonality,
+    exercise: u8,
+    period: Period,
+) -> QueryResult<Confusion> {
+    let mut confusion = Confusion::default();
+    let respell = |pitch: Pitch| tonality.altered(tonality.altered_degree(pitch));
+
+    for (answer, _) in answers(conn, period, Some((tonality, exercise)))? {
+        let played = match answer.note.parse::<Note>() {
+            Ok(note) => respell(note.pitch),
+            Err(_) => continue,
+        };
+
+        let given = if answer.correct {
+            Some(played)
+        } else {
+            answer.answer.parse::<Pitch>().ok().map(respell)
+        };
+
+        if let Some(given) = given {
+            *confusion.counts.entry((played, given)).or_insert(0) += 1;
+        }
+    }
+
+    Ok(confusion)
+}
+
+// Logged answers in the period with the tonalities of their games,
+// only from the tonality and the exercise if any
+fn answers(
+    conn: &SqliteConnection,
+    period: Period,
+    game: Option<(Tonality, u8)>,
+) -> QueryResult<Vec<(Answer, String)>> {
+    let mut query = octave_answers::table
+        .inner_join(octave_games::table)
+        .select((octave_answers::all_columns, octave_games::tonality))
+        .order(octave_answers::id)
+        .into_boxed();
+
+    if let Some((tonality, exercise)) = game {
+        query = query
+            .filter(octave_games::tonality.eq(tonality.to_string()))
+            .filter(octave_answers::exercise.eq(exercise as i32));
+    }
+
+    if let Some(since) = period.since {
+        query = query.filter(octave_answers::created_at.ge(since));
+    }
+    if let Some(until) = period.until {
+        query = query.filter(octave_answers::created_at.lt(until));
+    }
+
+    query.load(conn)
+}
+
+fn key(group: Group, answer: &Answer, tonality: &str) -> Option<Key> {
+    let note = || answer.note.parse::<Note>().ok();
+    let key = match group {
+        Group::Degree => {
+            let tonality = tonality.parse::<Tonality>().ok()?;
+            Key::Degree(tonality.altered_degree(note()?.pitch))
+        }
+        Group::Pitch => Key::Pitch(note()?.pitch),
+        Group::Octave => Key::Octave(note()?.octave),
+        Group::Tonality => Key::Tonality(tonality.to_owned()),
+        Group::Exercise => Key::Exercise(answer.exercise as u8),
+    };
+
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use diesel;
+    use games::octaves::models::NewAnswer;
+    use Database;
+
+    fn insert_game(conn: &SqliteConnection, tonality: &str) -> i32 {
+        diesel::insert_into(octave_games::table)
+            .values(octave_games::tonality.eq(tonality))
+            .execute(conn)
+            .unwrap();
+
+        octave_games::table
+            .select(octave_games::id)
+            .order(octave_games::id.desc())
+            .first(conn)
+            .unwrap()
+    }
+
+    fn insert_answer(conn: &SqliteConnection, game_id: i32, note: &str, correct: bool, ms: i32) {
+        let answer = if correct { note[..note.len() - 1].to_owned() } else { "E".to_owned() };
+        insert_answer_to(conn, game_id, 1, note, &answer, correct, ms);
+    }
+
+    fn insert_answer_to(
+        conn: &SqliteConnection,
+        game_id: i32,
+        exercise: i32,
+        note: &str,
+        answer: &str,
+        correct: bool,
+        ms: i32,
+    ) {
+        let answer = NewAnswer {
+            game_id,
+            exercise,
+            note: note.to_owned(),
+            answer: answer.to_owned(),
+            correct,
+            response_ms: ms,
+            repeats: 0,
+        };
+
+        diesel::insert_into(octave_answers::table)
+            .values(&answer)
+            .execute(conn)
+            .unwrap();
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn database() -> Database {
+        let db = Database::in_memory().unwrap();
+        {
+            let conn = db.connection();
+
+            let game_id = insert_game(conn, "Cmaj");
+            insert_answer(conn, game_id, "F4", true, 1000);
+            insert_answer(conn, game_id, "F4", false, 3000);
+            insert_answer(conn, game_id, "B3", false, 2000);
+
+            let game_id = insert_game(conn, "Gmaj");
+            insert_answer(conn, game_id, "C4", true, 500);
+            insert_answer(conn, game_id, "F#4", true, 1500);
+            // Out of the scale
+            insert_answer(conn, game_id, "F4", true, 1000);
+        }
+        db
+    }
+
+    #[test]
+    fn accuracy_by_degree() {
+        let db = database();
+        let stats = accuracy(db.connection(), Group::Degree, Period::all()).unwrap();
+
+        assert_eq!(
+            stats,
+            vec![
+                Accuracy {
+                    key: Key::Degree(AlteredDegree::new(4, 0)),
+                    right_count: 2,
+                    total_count: 3,
+                    response_time: Duration::from_millis(1500),
+                },
+                // Out of the scale of G major
+                Accuracy {
+                    key: Key::Degree(AlteredDegree::new(7, -1)),
+                    right_count: 1,
+                    total_count: 1,
+                    response_time: Duration::from_millis(1000),
+                },
+                Accuracy {
+                    key: Key::Degree(AlteredDegree::new(7, 0)),
+                    right_count: 1,
+                    total_count: 2,
+                    response_time: Duration::from_millis(1750),
+                },
+            ]
+        );
+        assert_eq!(stats[1].key.to_string(), "b7");
+        assert_eq!(stats[2].ratio(), 0.5);
+    }
+
+    #[test]
+    fn accuracy_by_pitch_and_octave() {
+        let db = database();
+
+        let stats = accuracy(db.connection(), Group::Pitch, Period::all()).unwrap();
+        let keys = stats.iter().map(|s| s.key.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                Key::Pitch(Pitch::C),
+                Key::Pitch(Pitch::F),
+                Key::Pitch(Pitch::Fsharp),
+                Key::Pitch(Pitch::B),
+            ]
+        );
+        assert_eq!(stats[1].right_count, 2);
+        assert_eq!(stats[1].total_count, 3);
+
+        let stats = accuracy(db.connection(), Group::Octave, Period::all()).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].key, Key::Octave(Octave::Small));
+        assert_eq!(stats[1].total_count, 5);
+    }
+
+    #[test]
+    fn accuracy_by_tonality_and_exercise() {
+        let db = database();
+
+        let stats = accuracy(db.connection(), Group::Tonality, Period::all()).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].key.to_string(), "Cmaj");
+        assert_eq!(stats[0].right_count, 1);
+        assert_eq!(stats[1].right_count, 3);
+
+        let stats = accuracy(db.connection(), Group::Exercise, Period::all()).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].total_count, 6);
+    }
+
+    #[test]
+    fn accuracy_in_period() {
+        let db = database();
+        let old = date(2018, 1, 10).and_hms_opt(12, 0, 0).unwrap();
+        diesel::update(octave_answers::table.filter(octave_answers::note.eq("B3")))
+            .set(octave_answers::created_at.eq(old))
+            .execute(db.connection())
+            .unwrap();
+
+        let seventh = Key::Degree(AlteredDegree::new(7, 0));
+        let stats = accuracy(db.connection(), Group::Degree, Period::last_days(30)).unwrap();
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[2].key, seventh);
+        assert_eq!(stats[2].right_count, 1);
+        assert_eq!(stats[2].total_count, 1);
+
+        let january = Period::new(
+            date(2018, 1, 1).and_hms_opt(0, 0, 0).unwrap(),
+            date(2018, 2, 1).and_hms_opt(0, 0, 0).unwrap(),
+        );
+        let stats = accuracy(db.connection(), Group::Degree, january).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].key, seventh);
+        assert_eq!(stats[0].right_count, 0);
+    }
+
+    #[test]
+    fn confusion_matrix() {
+        let db = database();
+        let conn = db.connection();
+        let game_id = insert_game(conn, "Cmaj");
+        insert_answer_to(conn, game_id, 1, "F4", "C#", false, 1000);
+        // Another exercise
+        insert_answer_to(conn, game_id, 2, "F4", "G", false, 1000);
+
+        let c_major = Tonality::major(Pitch::C);
+        let matrix = confusion(conn, c_major, 1, Period::all()).unwrap();
+        assert_eq!(
+            matrix.pitches(),
+            vec![Pitch::Dflat, Pitch::E, Pitch::F, Pitch::B]
+        );
+        assert_eq!(matrix.count(Pitch::F, Pitch::F), 1);
+        assert_eq!(matrix.count(Pitch::F, Pitch::E), 1);
+        // The b2 of C major
+        assert_eq!(matrix.count(Pitch::F, Pitch::Dflat), 1);
+        assert_eq!(matrix.count(Pitch::F, Pitch::G), 0);
+        assert_eq!(matrix.count(Pitch::B, Pitch::E), 1);
+        assert_eq!(matrix.total(Pitch::F), 3);
+
+        let matrix = confusion(conn, c_major, 3, Period::all()).unwrap();
+        assert!(matrix.is_empty());
+    }
+
+    #[test]
+    fn confusion_matrix_in_flat_key() {
+        let db = Database::in_memory().unwrap();
+        let conn = db.connection();
+        let game_id = insert_game(conn, "Dbmaj");
+        insert_answer_to(conn, game_id, 1, "Db4", "C#", true, 1000);
+        insert_answer_to(conn, game_id, 1, "C4", "C#", false, 1000);
+        insert_answer_to(conn, game_id, 1, "Gb4", "F", false, 1000);
+
+        let matrix = confusion(conn, Tonality::major(Pitch::Dflat), 1, Period::all()).unwrap();
+        assert_eq!(
+            matrix.pitches(),
+            vec![Pitch::C, Pitch::Dflat, Pitch::F, Pitch::Gflat]
+        );
+        assert_eq!(matrix.count(Pitch::Dflat, Pitch::Dflat), 1);
+        assert_eq!(matrix.count(Pitch::C, Pitch::Dflat), 1);
+        assert_eq!(matrix.count(Pitch::Gflat, Pitch::F), 1);
+        assert_eq!(matrix.count(Pitch::C, Pitch::Csharp), 0);
+    }
+}
@@ -0,0 +1,163 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use games::octaves::note::{Mode, Note, Octave, Tonality};
+use wav::{self, SAMPLE_RATE};
+use xdg_dirs;
+
+// Seconds
+const NOTE_LENGTH: f32 = 1.5;
+const CHORD_LENGTH: f32 = 0.8;
+const ATTACK: f32 = 0.005;
+const RELEASE: f32 = 0.05;
+
+// Amplitudes of the partials, the upper ones also die away faster
+// which gives a struck-string sound
+const PARTIALS: [f32; 6] = [1.0, 0.5, 0.3, 0.2, 0.12, 0.08];
+
+// Voicings of I–IV–V–I as scale degrees from the tonic,
+// the bass is an octave below
+const CADENCE: [[i8; 4]; 4] = [
+    [-7, 0, 2, 4],
+    [-4, 0, 3, 5],
+    [-3, -1, 1, 4],
+    [-7, 0, 2, 4],
+];
+
+// Rendered notes are kept in the cache so each one is synthesized once
+pub fn note_sample(note: Note) -> io::Result<PathBuf> {
+    let path = xdg_dirs::SYNTH.join(format!("{}.wav", note));
+    cached(path, || render_note(note))
+}
+
+pub fn cadence_sample(tonality: Tonality) -> io::Result<PathBuf> {
+    let cadence = tonality.cadence();
+    let path = xdg_dirs::SYNTH.join(format!("IIVVIPAC - {}.wav", cadence));
+    cached(path, || render_cadence(cadence))
+}
+
+fn cached<F>(path: PathBuf, render: F) -> io::Result<PathBuf>
+where
+    F: FnOnce() -> Vec<f32>,
+{
+    if !path.exists() {
+        // Write aside first so an interrupted render is never played
+        let part = path.with_extension("part");
+        {
+            let mut file = fs::File::create(&part)?;
+            wav::write(&mut file, &render())?;
+        }
+        fs::rename(&part, &path)?;
+    }
+
+    Ok(path)
+}
+
+pub fn render_note(note: Note) -> Vec<f32> {
+    render_tones(&[note.frequency()], NOTE_LENGTH)
+}
+
+pub fn render_chord(notes: &[Note]) -> Vec<f32> {
+    let freqs = notes.iter().map(|n| n.frequency()).collect::<Vec<_>>();
+    render_tones(&freqs, NOTE_LENGTH)
+}
+
+// Perfect authentic cadence in the major or minor key of the tonality,
+// the minor one takes the leading tone from the harmonic minor
+pub fn render_cadence(tonality: Tonality) -> Vec<f32> {
+    let tonality = tonality.cadence();
+    let formula = if tonality.mode.is_minor() {
+        Mode::HarmonicMinor.formula()
+    } else {
+        Mode::Major.formula()
+    };
+    let tonic = Note {
+        octave: Octave::Small,
+        pitch: tonality.key,
+    }.frequency();
+
+    let degree_freq = |degree: i8| {
+        let octave = if degree < 0 {
+            (degree - 6) / 7
+        } else {
+            degree / 7
+        };
+        let index = (degree - octave * 7) as usize;
+        let semitones = formula[..index].iter().sum::<u8>() as i8 + octave * 12;
+
+        tonic * 2f32.powf(semitones as f32 / 12.0)
+    };
+
+    let mut samples = Vec::new();
+    for (i, chord) in CADENCE.iter().enumerate() {
+        let freqs = chord.iter().map(|&d| degree_freq(d)).collect::<Vec<_>>();
+        let length = if i == CADENCE.len() - 1 {
+            NOTE_LENGTH
+        } else {
+            CHORD_LENGTH
+        };
+
+        samples.extend(render_tones(&freqs, length));
+    }
+
+    samples
+}
+
+fn render_tones(freqs: &[f32], length: f32) -> Vec<f32> {
+    let count = (length * SAMPLE_RATE as f32) as usize;
+    let gain = 0.8 / (freqs.len() as f32 * PARTIALS.iter().sum::<f32>());
+    let nyquist = SAMPLE_RATE as f32 / 2.0;
+
+    (0..count)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let envelope = (t / ATTACK).min(1.0) * ((length - t) / RELEASE).min(1.0);
+
+            let mut value = 0.0;
+            for freq in freqs.iter() {
+                for (k, amp) in PARTIALS.iter().enumerate() {
+                    let partial = freq * (k + 1) as f32;
+                    if partial >= nyquist {
+                        break;
+                    }
+
+                    let decay = (-t * (1.5 + k as f32)).exp();
+                    value += amp * decay * (2.0 * PI * partial * t).sin();
+                }
+            }
+
+            value * gain * envelope
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use games::octaves::note::Pitch;
+
+    #[test]
+    fn render_note_length() {
+        let note = "A4".parse::<Note>().unwrap();
+        let samples = render_note(note);
+
+        assert_eq!(samples.len(), (NOTE_LENGTH * SAMPLE_RATE as f32) as usize);
+        assert!(samples.iter().all(|s| s.abs() <= 1.0));
+        assert!(samples.iter().any(|s| s.abs() > 0.1));
+        // Starts and ends in silence
+        assert_eq!(samples[0], 0.0);
+        assert!(samples.last().unwrap().abs() < 0.001);
+    }
+
+    #[test]
+    fn render_cadence_length() {
+        let samples = render_cadence(Tonality::new(Pitch::Gsharp, Mode::Phrygian));
+        let chord = (CHORD_LENGTH * SAMPLE_RATE as f32) as usize;
+        let note = (NOTE_LENGTH * SAMPLE_RATE as f32) as usize;
+
+        assert_eq!(samples.len(), 3 * chord + note);
+        assert!(samples.iter().all(|s| s.abs() <= 1.0));
+    }
+}
@@ -0,0 +1,65 @@
+use std::io::{self, Write};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+const CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+
+// Write mono samples in [-1, 1] as a 16-bit PCM WAV file
+pub fn write<W: Write>(out: &mut W, samples: &[f32]) -> io::Result<()> {
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+    let data_len = samples.len() as u32 * block_align as u32;
+
+    out.write_all(b"RIFF")?;
+    write_u32(out, 36 + data_len)?;
+    out.write_all(b"WAVE")?;
+
+    out.write_all(b"fmt ")?;
+    write_u32(out, 16)?;
+    // PCM
+    write_u16(out, 1)?;
+    write_u16(out, CHANNELS)?;
+    write_u32(out, SAMPLE_RATE)?;
+    write_u32(out, SAMPLE_RATE * block_align as u32)?;
+    write_u16(out, block_align)?;
+    write_u16(out, BITS_PER_SAMPLE)?;
+
+    out.write_all(b"data")?;
+    write_u32(out, data_len)?;
+
+    let mut data = Vec::with_capacity(data_len as usize);
+    for sample in samples.iter() {
+        let value = (sample.max(-1.0).min(1.0) * i16::max_value() as f32) as i16;
+        data.push(value as u8);
+        data.push((value >> 8) as u8);
+    }
+    out.write_all(&data)
+}
+
+fn write_u16<W: Write>(out: &mut W, n: u16) -> io::Result<()> {
+    out.write_all(&[n as u8, (n >> 8) as u8])
+}
+
+fn write_u32<W: Write>(out: &mut W, n: u32) -> io::Result<()> {
+    out.write_all(&[n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_header_and_data() {
+        let mut out = Vec::new();
+        write(&mut out, &[0.0, 1.0, -1.0, 2.0]).unwrap();
+
+        assert_eq!(out.len(), 44 + 4 * 2);
+        assert_eq!(&out[0..4], b"RIFF");
+        assert_eq!(&out[4..8], &[44, 0, 0, 0]);
+        assert_eq!(&out[8..16], b"WAVEfmt ");
+        assert_eq!(&out[24..28], &[0x44, 0xac, 0, 0]);
+        assert_eq!(&out[36..40], b"data");
+        assert_eq!(&out[40..44], &[8, 0, 0, 0]);
+        assert_eq!(&out[44..], &[0, 0, 0xff, 0x7f, 0x01, 0x80, 0xff, 0x7f]);
+    }
+}